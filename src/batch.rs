@@ -0,0 +1,282 @@
+use crate::report::{
+    CSV_HEADER, ReportDestination, ReportFormat, count_verdicts, csv_row, write_markdown_report,
+};
+use crate::{
+    CheckOptions, CheckResult, Error, ProgressSink, Report, SiteData, Verdict,
+    check_username_with_sink, write_txt_report,
+};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+/// How a single candidate fared across every checked site
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CandidateSummary {
+    pub username: String,
+    pub available: usize,
+    pub taken: usize,
+    /// Sites that gave no clear answer: errors, challenges, invalid names...
+    pub other: usize,
+    pub checked: usize,
+}
+
+impl CandidateSummary {
+    pub fn new(username: &str, results: &[CheckResult]) -> Self {
+        let available = count_verdicts(results, Verdict::Available);
+        let taken = count_verdicts(results, Verdict::Taken);
+        CandidateSummary {
+            username: username.to_string(),
+            available,
+            taken,
+            other: results.len() - available - taken,
+            checked: results.len(),
+        }
+    }
+}
+
+/// Combined results of checking several usernames in one run
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchReport {
+    pub generated_at: String,
+    /// Candidates ranked by the number of sites they are free on
+    pub summary: Vec<CandidateSummary>,
+    pub reports: Vec<Report>,
+}
+
+/// Reads one username per line, skipping blank lines, `#` comments and duplicates
+pub fn read_usernames(reader: impl BufRead) -> io::Result<Vec<String>> {
+    let mut seen = HashSet::new();
+    let mut usernames = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let username = line.trim();
+        if username.is_empty() || username.starts_with('#') {
+            continue;
+        }
+        if seen.insert(username.to_string()) {
+            usernames.push(username.to_string());
+        }
+    }
+    Ok(usernames)
+}
+
+/// Sorts candidates by available sites, then by fewest taken, then by name
+pub fn rank_candidates(summary: &mut [CandidateSummary]) {
+    summary.sort_by(|a, b| {
+        b.available
+            .cmp(&a.available)
+            .then(a.taken.cmp(&b.taken))
+            .then_with(|| a.username.cmp(&b.username))
+    });
+}
+
+/// Checks every username in turn, sharing the client, site data and
+/// rate limiter between them
+pub async fn check_usernames(
+    client: &Client,
+    usernames: &[String],
+    sites_data: &[SiteData],
+    options: &CheckOptions,
+    sink: &dyn ProgressSink,
+) -> BatchReport {
+    let mut reports = Vec::with_capacity(usernames.len());
+    for username in usernames {
+        let results = check_username_with_sink(client, username, sites_data, options, sink).await;
+        reports.push(Report::new(username, results));
+    }
+
+    let mut summary: Vec<CandidateSummary> = reports
+        .iter()
+        .map(|report| CandidateSummary::new(&report.username, &report.results))
+        .collect();
+    rank_candidates(&mut summary);
+
+    BatchReport {
+        generated_at: chrono::Local::now().to_string(),
+        summary,
+        reports,
+    }
+}
+
+/// Writes the ranking table followed by the per-username reports
+pub fn write_batch_txt_report(file: &mut impl Write, report: &BatchReport) -> io::Result<()> {
+    writeln!(file, "Batch availability report")?;
+    writeln!(file, "Generated on: {}", report.generated_at)?;
+    writeln!(file, "{}", "=".repeat(80))?;
+    write_ranking(file, &report.summary)?;
+    for username_report in &report.reports {
+        writeln!(file, "{}", "=".repeat(80))?;
+        write_txt_report(file, &username_report.username, &username_report.results)?;
+    }
+    Ok(())
+}
+
+/// Writes every result in one table, the `CSV_HEADER` columns preceded by
+/// the username
+pub fn write_batch_csv_report(file: &mut impl Write, report: &BatchReport) -> Result<(), Error> {
+    let mut writer = csv::Writer::from_writer(file);
+    writer.write_record(std::iter::once("username").chain(CSV_HEADER))?;
+    for username_report in &report.reports {
+        for result in &username_report.results {
+            let row = csv_row(result);
+            writer.write_record(std::iter::once(&username_report.username).chain(&row))?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the ranking as a table followed by the per-username reports
+pub fn write_batch_markdown_report(file: &mut impl Write, report: &BatchReport) -> io::Result<()> {
+    writeln!(file, "# Batch availability report")?;
+    writeln!(file)?;
+    writeln!(file, "Generated on: {}", report.generated_at)?;
+    writeln!(file)?;
+    writeln!(file, "| Rank | Username | Available | Taken | Other |")?;
+    writeln!(file, "| ---: | --- | ---: | ---: | ---: |")?;
+    for (rank, candidate) in report.summary.iter().enumerate() {
+        writeln!(
+            file,
+            "| {} | `{}` | {} | {} | {} |",
+            rank + 1,
+            candidate.username,
+            candidate.available,
+            candidate.taken,
+            candidate.other
+        )?;
+    }
+    for username_report in &report.reports {
+        writeln!(file)?;
+        write_markdown_report(file, &username_report.username, &username_report.results)?;
+    }
+    Ok(())
+}
+
+/// Writes the ranked candidate table
+pub fn write_ranking(file: &mut impl Write, summary: &[CandidateSummary]) -> io::Result<()> {
+    writeln!(
+        file,
+        "{:>4}  {:<30} {:>9} {:>6} {:>6}",
+        "Rank", "Username", "Available", "Taken", "Other"
+    )?;
+    for (rank, candidate) in summary.iter().enumerate() {
+        writeln!(
+            file,
+            "{:>4}  {:<30} {:>9} {:>6} {:>6}",
+            rank + 1,
+            candidate.username,
+            candidate.available,
+            candidate.taken,
+            candidate.other
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the combined report to `destination`, returning the path it was
+/// saved to unless it went to stdout. See `ReportFormat::supports_batch`.
+pub fn save_batch_report(
+    destination: &ReportDestination,
+    format: ReportFormat,
+    name: &str,
+    report: &BatchReport,
+) -> Result<Option<PathBuf>, Error> {
+    if !format.supports_batch() {
+        return Err(Error::UnsupportedFormat(unsupported_batch_format(format)));
+    }
+    let (mut file, path) = destination.create(name, format)?;
+    match format {
+        ReportFormat::Json => {
+            serde_json::to_writer_pretty(&mut file, report)?;
+            writeln!(file)?;
+        }
+        ReportFormat::Csv => write_batch_csv_report(&mut file, report)?,
+        ReportFormat::Markdown => write_batch_markdown_report(&mut file, report)?,
+        _ => write_batch_txt_report(&mut file, report)?,
+    }
+    file.flush()?;
+    Ok(path)
+}
+
+/// Why `format` cannot hold a batch report
+pub fn unsupported_batch_format(format: ReportFormat) -> String {
+    format!(
+        "Batch reports support txt, json, csv and markdown, not {}",
+        format.extension()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::result;
+
+    fn candidate(username: &str, available: usize, taken: usize) -> CandidateSummary {
+        CandidateSummary {
+            username: username.to_string(),
+            available,
+            taken,
+            other: 0,
+            checked: available + taken,
+        }
+    }
+
+    #[test]
+    fn test_read_usernames() {
+        let input = "alice\n\n  bob  \n# shortlisted later\nalice\ncarol\n";
+        let usernames = read_usernames(input.as_bytes()).unwrap();
+        assert_eq!(usernames, ["alice", "bob", "carol"]);
+    }
+
+    #[test]
+    fn test_rank_candidates() {
+        let mut summary = vec![
+            candidate("zed", 3, 1),
+            candidate("amy", 5, 4),
+            candidate("bob", 5, 2),
+            candidate("abe", 3, 1),
+        ];
+        rank_candidates(&mut summary);
+        let order: Vec<&str> = summary.iter().map(|c| c.username.as_str()).collect();
+        assert_eq!(order, ["bob", "amy", "abe", "zed"]);
+    }
+
+    #[test]
+    fn test_batch_csv_and_markdown() {
+        let reports = vec![
+            Report::new("alice", vec![result("GitHub", Verdict::Taken)]),
+            Report::new("bob", vec![result("GitHub", Verdict::Available)]),
+        ];
+        let mut summary: Vec<CandidateSummary> = reports
+            .iter()
+            .map(|report| CandidateSummary::new(&report.username, &report.results))
+            .collect();
+        rank_candidates(&mut summary);
+        let report = BatchReport {
+            generated_at: "2025-03-01".to_string(),
+            summary,
+            reports,
+        };
+
+        let mut out = Vec::new();
+        write_batch_csv_report(&mut out, &report).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("username,site,category,verdict,"));
+        assert!(lines[1].starts_with("alice,GitHub,coding,Taken,200,"));
+        assert!(lines[2].starts_with("bob,GitHub,coding,Available,200,"));
+
+        let mut out = Vec::new();
+        write_batch_markdown_report(&mut out, &report).unwrap();
+        let markdown = String::from_utf8(out).unwrap();
+        assert!(markdown.contains("| 1 | `bob` | 1 | 0 | 0 |"));
+        assert!(markdown.contains("| 2 | `alice` | 0 | 1 | 0 |"));
+        assert!(markdown.contains("# Username availability report for `alice`"));
+
+        assert!(ReportFormat::Csv.supports_batch());
+        assert!(!ReportFormat::Html.supports_batch());
+    }
+}
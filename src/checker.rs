@@ -0,0 +1,218 @@
+use crate::{
+    CheckOptions, CheckResult, Error, HostRateLimiter, NullSink, ProgressSink, ProxyPool,
+    RateLimitPolicy, Report, RetryPolicy, Rotation, SiteData, SiteFilter, build_client,
+    check_username_with_sink,
+};
+use reqwest::Client;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A configured checking engine for embedding in other programs.
+///
+/// ```no_run
+/// # async fn scan() -> Result<(), firststep_name_lib::Error> {
+/// use firststep_name_lib::{Checker, SitesFile};
+///
+/// let sites = SitesFile::load("wmn-data.json")?;
+/// let checker = Checker::builder().concurrency(20).build()?;
+/// for result in checker.check("johndoe", sites.sites()).await {
+///     println!("{}: {}", result.site(), result.verdict());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Checker {
+    client: Client,
+    options: CheckOptions,
+    sink: Arc<dyn ProgressSink>,
+}
+
+impl Checker {
+    pub fn builder() -> CheckerBuilder {
+        CheckerBuilder::default()
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn options(&self) -> &CheckOptions {
+        &self.options
+    }
+
+    /// Checks `username` against the sites selected by the filter, reporting
+    /// progress to the configured sink
+    pub async fn check(&self, username: &str, sites: &[SiteData]) -> Vec<CheckResult> {
+        self.check_with_sink(username, sites, &*self.sink).await
+    }
+
+    /// Like `check`, reporting progress to `sink` instead
+    pub async fn check_with_sink(
+        &self,
+        username: &str,
+        sites: &[SiteData],
+        sink: &dyn ProgressSink,
+    ) -> Vec<CheckResult> {
+        check_username_with_sink(&self.client, username, sites, &self.options, sink).await
+    }
+
+    /// Like `check`, wrapping the results into a report
+    pub async fn report(&self, username: &str, sites: &[SiteData]) -> Report {
+        Report::new(username, self.check(username, sites).await)
+    }
+}
+
+/// Configures a `Checker`; anything left unset keeps the CLI defaults
+#[derive(Default)]
+pub struct CheckerBuilder {
+    client: Option<Client>,
+    proxies: Vec<String>,
+    rotation: Rotation,
+    options: CheckOptions,
+    sink: Option<Arc<dyn ProgressSink>>,
+}
+
+impl CheckerBuilder {
+    /// HTTP client used for direct requests, instead of one built by `build_client`
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Time allowed for a single request, body included
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = timeout;
+        self
+    }
+
+    /// Number of requests kept in flight at any time
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.options.concurrency = concurrency;
+        self
+    }
+
+    /// Emit results in site order instead of completion order
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.options.ordered = ordered;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.options.retry = retry;
+        self
+    }
+
+    pub fn rate_limit(mut self, policy: RateLimitPolicy) -> Self {
+        self.options.rate_limiter = Arc::new(HostRateLimiter::new(policy));
+        self
+    }
+
+    /// Proxy URLs rotated per request; checks never go out directly when set
+    pub fn proxies(mut self, urls: Vec<String>, rotation: Rotation) -> Self {
+        self.proxies = urls;
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn filter(mut self, filter: SiteFilter) -> Self {
+        self.options.filter = filter;
+        self
+    }
+
+    /// Also probe every site with a random control username
+    pub fn control_check(mut self, control_check: bool) -> Self {
+        self.options.control_check = control_check;
+        self
+    }
+
+    /// Receives per-site progress, nothing is reported by default
+    pub fn sink(mut self, sink: impl ProgressSink + 'static) -> Self {
+        self.sink = Some(Arc::new(sink));
+        self
+    }
+
+    /// Builds the client and proxy pool, failing on invalid proxy URLs
+    pub fn build(self) -> Result<Checker, Error> {
+        let mut options = self.options;
+        if !self.proxies.is_empty() {
+            options.proxies = Some(Arc::new(ProxyPool::new(&self.proxies, self.rotation)?));
+        }
+        let client = match self.client {
+            Some(client) => client,
+            None => build_client(self.proxies.first().map(String::as_str))?,
+        };
+        Ok(Checker {
+            client,
+            options,
+            sink: self.sink.unwrap_or_else(|| Arc::new(NullSink)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Verdict;
+    use crate::test_util::{profile, serve, test_site};
+    use poem::{Route, get};
+    use std::sync::Mutex;
+
+    #[derive(Default, Clone)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl ProgressSink for Recorder {
+        fn on_result(&self, result: &CheckResult, _completed: usize, _total: usize) {
+            self.0.lock().unwrap().push(result.site().to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checker_builder() {
+        let base = serve(Route::new().at("/:name", get(profile))).await;
+        let sites = [
+            test_site(format!("{}/{{account}}", base)),
+            SiteData::new(
+                "Other",
+                format!("{}/{{account}}", base),
+                200,
+                "\"id\":",
+                404,
+                "Not Found",
+            )
+            .with_category("social")
+            .with_uri_pretty("https://other.example/{account}"),
+        ];
+        let recorder = Recorder::default();
+        let checker = Checker::builder()
+            .concurrency(1)
+            .ordered(true)
+            .timeout(Duration::from_secs(2))
+            .retry(RetryPolicy::none())
+            .filter(SiteFilter {
+                categories: vec!["social".to_string()],
+                ..SiteFilter::default()
+            })
+            .sink(recorder.clone())
+            .build()
+            .unwrap();
+        assert_eq!(checker.options().timeout, Duration::from_secs(2));
+
+        let results = checker.check("janedoe", &sites).await;
+        assert_eq!(results.len(), 1, "the filter keeps only the social site");
+        let result = &results[0];
+        assert_eq!(result.site(), "Other");
+        assert_eq!(result.category(), "social");
+        assert_eq!(result.verdict(), Verdict::Available);
+        assert_eq!(result.http_status(), Some(404));
+        assert_eq!(result.profile_url(), "https://other.example/janedoe");
+        assert_eq!(*recorder.0.lock().unwrap(), ["Other"]);
+
+        let report = Checker::builder()
+            .build()
+            .unwrap()
+            .report("johndoe", &sites)
+            .await;
+        assert_eq!(report.results.len(), 2);
+        assert!(report.results.iter().all(|r| r.verdict() == Verdict::Taken));
+    }
+}
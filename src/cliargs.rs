@@ -1,38 +1,342 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use firststep_name_lib::filter::NamePattern;
+use firststep_name_lib::proxy::read_proxy_list;
+use firststep_name_lib::report::DEFAULT_FILENAME_TEMPLATE;
+use firststep_name_lib::{
+    CheckOptions, CompletionWebhooks, HostRateLimiter, ProxyPool, RateLimitPolicy,
+    ReportDestination, RetryPolicy, Rotation, SiteFilter,
+};
+use std::error::Error;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[clap(
     author,
     version,
-    about = "Checks username availability across social networks"
+    about = "Checks username availability across social networks",
+    subcommand_negates_reqs = true
 )]
 pub struct CliArgs {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
     /// Username to check
-    #[clap(short, long, required = true)]
-    pub username: String,
+    #[clap(short, long, required_unless_present = "usernames_file")]
+    pub username: Option<String>,
+
+    /// File with one username per line to check in a single run, `-` for stdin
+    #[clap(long, conflicts_with = "username")]
+    pub usernames_file: Option<PathBuf>,
 
     /// JSON file with site data
-    #[clap(short, long, default_value = "social_sites.json")]
+    #[clap(short, long, default_value = "social_sites.json", global = true)]
     pub file: PathBuf,
 
-    /// Output format (txt, json or web)
-    #[clap(short, long, default_value = "txt", value_parser = ["txt", "json", "web"])]
+    /// Output format (txt, json, csv, markdown, html, ndjson or web); ndjson streams one result per line to stdout
+    #[clap(
+        short,
+        long,
+        default_value = "txt",
+        value_parser = ["txt", "json", "csv", "markdown", "html", "ndjson", "web"],
+        global = true
+    )]
     pub output: String,
 
+    /// Report file name, with {username}, {date} and {format} placeholders, or - for stdout
+    #[clap(long, default_value = DEFAULT_FILENAME_TEMPLATE, global = true)]
+    pub output_file: String,
+
+    /// Directory to write reports to, created when missing
+    #[clap(long, global = true)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Add a timestamp to the report name instead of overwriting an existing report
+    #[clap(long, global = true)]
+    pub no_clobber: bool,
+
     /// Download the latest sites data from GitHub
-    #[clap(short, long)]
+    #[clap(short, long, global = true)]
     pub download: bool,
 
-    /// Number of threads to use for checking (default: 10)
-    #[clap(short, long, default_value = "10", value_parser = thread_count_parser)]
+    /// Number of requests kept in flight while checking (default: 10)
+    #[clap(short, long, default_value = "10", value_parser = thread_count_parser, global = true)]
     pub threads: usize,
+
+    /// Print results in data file order instead of as they complete
+    #[clap(long)]
+    pub ordered: bool,
+
+    /// Maximum requests per second sent to a single host (0 disables the limit)
+    #[clap(long, default_value = "5", global = true)]
+    pub rate_limit: f64,
+
+    /// Number of back-to-back requests a single host may receive
+    #[clap(long, default_value = "5", global = true)]
+    pub burst: u32,
+
+    /// Seconds to leave a host alone after it answers 429 Too Many Requests
+    #[clap(long, default_value = "60", global = true)]
+    pub cooldown: u64,
+
+    /// Maximum requests per site when retrying timeouts, resets, 429 and 5xx (1 disables retries)
+    #[clap(long, default_value = "3", global = true)]
+    pub max_attempts: u32,
+
+    /// Proxy for all requests, e.g. socks5://127.0.0.1:9050 for a local Tor daemon
+    #[clap(long, global = true)]
+    pub proxy: Option<String>,
+
+    /// File with one proxy URL per line, rotated per request
+    #[clap(long, global = true)]
+    pub proxy_list: Option<PathBuf>,
+
+    /// How to pick the proxy for each request (round-robin or random)
+    #[clap(
+        long,
+        default_value = "round-robin",
+        value_parser = Rotation::from_str,
+        global = true
+    )]
+    pub proxy_rotation: Rotation,
+
+    /// Skip sites that declare a bot protection such as Cloudflare
+    #[clap(long, global = true)]
+    pub skip_protected: bool,
+
+    /// Only check sites in these categories (repeatable or comma separated)
+    #[clap(long = "category", value_delimiter = ',', global = true)]
+    pub categories: Vec<String>,
+
+    /// Never check sites in these categories
+    #[clap(long = "exclude-category", value_delimiter = ',', global = true)]
+    pub exclude_categories: Vec<String>,
+
+    /// Only check sites whose name matches these globs or /regexes/,
+    /// e.g. "git*" or "/^git(hub|lab)$/"
+    #[clap(
+        long = "site",
+        value_delimiter = ',',
+        value_parser = site_pattern_parser,
+        global = true
+    )]
+    pub sites: Vec<String>,
+
+    /// Never check sites whose name matches these globs or /regexes/
+    #[clap(
+        long = "exclude-site",
+        value_delimiter = ',',
+        value_parser = site_pattern_parser,
+        global = true
+    )]
+    pub exclude_sites: Vec<String>,
+
+    /// Include the "xx NSFW xx" category, which is skipped by default
+    #[clap(long, global = true)]
+    pub nsfw: bool,
+
+    /// SQLite file to record every scan in and read the history command from;
+    /// nothing is recorded unless it is given
+    #[clap(long, global = true)]
+    pub history_db: Option<PathBuf>,
+
+    /// POST a JSON summary of every finished scan to this URL (repeatable)
+    #[clap(long, global = true)]
+    pub completion_webhook: Vec<String>,
+
+    /// Sign completion webhooks with an HMAC-SHA256 of the body keyed with this secret
+    #[clap(long, global = true, requires = "completion_webhook")]
+    pub webhook_secret: Option<String>,
+
+    /// Also check a random control username and flag sites that claim it is taken too
+    #[clap(long, global = true)]
+    pub control: bool,
+
+    /// Time allowed for a single site request, e.g. 10s or 1m
+    #[clap(long, default_value = "10s", value_parser = interval_parser, global = true)]
+    pub timeout: Duration,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Maintain the site data file
+    Sites {
+        #[clap(subcommand)]
+        command: SitesCommand,
+    },
+    /// Compare two JSON reports of the same username
+    Diff {
+        /// Older report
+        before: PathBuf,
+
+        /// Newer report
+        after: PathBuf,
+
+        /// Output format of the comparison
+        #[clap(long, default_value = "text", value_parser = ["text", "json", "markdown"])]
+        format: String,
+    },
+    /// Show the recorded availability timeline of a username per site
+    History {
+        username: String,
+
+        /// Only show the checks where the verdict changed
+        #[clap(long)]
+        changes: bool,
+
+        /// Output format of the timeline
+        #[clap(long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+    },
+    /// Re-check usernames on an interval and announce sites where they become available
+    Watch {
+        /// Usernames to watch
+        #[clap(required = true)]
+        usernames: Vec<String>,
+
+        /// Time between rounds, e.g. 90s, 15m, 6h or 1d
+        #[clap(long, default_value = "15m", value_parser = interval_parser)]
+        every: Duration,
+
+        /// URL to POST each release to as JSON (repeatable)
+        #[clap(long)]
+        webhook: Vec<String>,
+
+        /// Shell command to run for each release, with the release as JSON on stdin (repeatable)
+        #[clap(long)]
+        exec: Vec<String>,
+    },
+    /// Generate variants of a name and rank them by availability
+    Variants {
+        /// Name to derive the variants from, e.g. "First Step"
+        base: String,
+
+        /// Maximum number of variants to check, the base name included
+        #[clap(long, default_value = "50")]
+        limit: usize,
+
+        /// Only print the generated variants without checking them
+        #[clap(long)]
+        list: bool,
+
+        /// Do not join words with separators or put them before affixes
+        #[clap(long)]
+        no_separators: bool,
+
+        /// Do not append suffixes such as "hq" or digits
+        #[clap(long)]
+        no_suffixes: bool,
+
+        /// Do not prepend prefixes such as "get" or "the"
+        #[clap(long)]
+        no_prefixes: bool,
+
+        /// Do not replace letters with look-alike digits
+        #[clap(long)]
+        no_leet: bool,
+
+        /// Do not shorten the name
+        #[clap(long)]
+        no_truncation: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SitesCommand {
+    /// Check every site with its known accounts and a random username
+    Selftest {
+        /// Write the JSON health report to this file instead of stdout
+        #[clap(long)]
+        report: Option<PathBuf>,
+
+        /// Number of known accounts to check per site
+        #[clap(long, default_value = "1")]
+        known: usize,
+    },
 }
 
 impl CliArgs {
     pub fn parse() -> Self {
         <Self as Parser>::parse()
     }
+
+    /// Per-host politeness policy built from the rate limit flags
+    pub fn rate_limit_policy(&self) -> RateLimitPolicy {
+        RateLimitPolicy {
+            requests_per_second: self.rate_limit,
+            burst: self.burst,
+            cooldown: Duration::from_secs(self.cooldown),
+        }
+    }
+
+    /// Proxy URLs from `--proxy` and `--proxy-list`, in that order
+    pub fn proxies(&self) -> std::io::Result<Vec<String>> {
+        let mut proxies: Vec<String> = self.proxy.iter().cloned().collect();
+        if let Some(path) = &self.proxy_list {
+            proxies.extend(read_proxy_list(path)?);
+        }
+        Ok(proxies)
+    }
+
+    /// Retry policy built from the retry flags
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts.max(1),
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Report location built from the output file flags
+    pub fn report_destination(&self) -> ReportDestination {
+        ReportDestination {
+            template: self.output_file.clone(),
+            dir: self.output_dir.clone(),
+            no_clobber: self.no_clobber,
+        }
+    }
+
+    /// History database to record scans in, if any
+    pub fn history_path(&self) -> Option<PathBuf> {
+        self.history_db.clone()
+    }
+
+    /// Completion webhooks from `--completion-webhook` and `--webhook-secret`
+    pub fn completion_webhooks(&self) -> CompletionWebhooks {
+        CompletionWebhooks::new(self.completion_webhook.clone(), self.webhook_secret.clone())
+    }
+
+    /// Site selection built from the category, site and protection flags
+    pub fn site_filter(&self) -> SiteFilter {
+        SiteFilter {
+            categories: self.categories.clone(),
+            exclude_categories: self.exclude_categories.clone(),
+            sites: self.sites.clone(),
+            exclude_sites: self.exclude_sites.clone(),
+            nsfw: self.nsfw,
+            skip_protected: self.skip_protected,
+        }
+    }
+
+    /// Scan options built from the concurrency, politeness and proxy flags
+    pub fn check_options(&self) -> Result<CheckOptions, Box<dyn Error>> {
+        let proxies = self.proxies()?;
+        Ok(CheckOptions {
+            concurrency: self.threads,
+            ordered: self.ordered,
+            rate_limiter: Arc::new(HostRateLimiter::new(self.rate_limit_policy())),
+            retry: self.retry_policy(),
+            proxies: if proxies.is_empty() {
+                None
+            } else {
+                Some(Arc::new(ProxyPool::new(&proxies, self.proxy_rotation)?))
+            },
+            filter: self.site_filter(),
+            control_check: self.control,
+            timeout: self.timeout,
+        })
+    }
 }
 
 /// Custom parser function to validate thread count is between 1 and 100
@@ -41,9 +345,59 @@ fn thread_count_parser(s: &str) -> Result<usize, String> {
         .parse()
         .map_err(|_| "Thread count must be a positive number".to_string())?;
 
-    if thread_count < 1 || thread_count >= 100 {
+    if !(1..100).contains(&thread_count) {
         return Err("Thread count must be between 1 and 99".to_string());
     }
 
     Ok(thread_count)
 }
+
+/// Rejects invalid `/regex/` site patterns up front
+fn site_pattern_parser(s: &str) -> Result<String, String> {
+    NamePattern::parse(s)
+        .map(|_| s.to_string())
+        .map_err(|e| format!("Invalid site pattern: {}", e))
+}
+
+/// Parses an interval such as `90s`, `15m`, `6h` or `1d`, plain numbers are seconds
+fn interval_parser(s: &str) -> Result<Duration, String> {
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => s.split_at(index),
+        None => (s, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid interval: {}", s))?;
+    let seconds = match unit {
+        "s" => number,
+        "m" => number * 60,
+        "h" => number * 60 * 60,
+        "d" => number * 60 * 60 * 24,
+        _ => return Err(format!("Unknown interval unit in {}, use s, m, h or d", s)),
+    };
+    if seconds == 0 {
+        return Err("Interval must be longer than zero".to_string());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        CliArgs::command().debug_assert();
+    }
+
+    #[test]
+    fn test_interval_parser() {
+        assert_eq!(interval_parser("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(interval_parser("15m"), Ok(Duration::from_secs(15 * 60)));
+        assert_eq!(interval_parser("1d"), Ok(Duration::from_secs(86_400)));
+        assert!(interval_parser("0h").is_err());
+        assert!(interval_parser("5w").is_err());
+        assert!(interval_parser("m").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{CheckError, CheckResult, Report, Verdict};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// A site whose verdict flipped between Taken and Available
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VerdictChange {
+    pub site: String,
+    pub before: Verdict,
+    pub after: Verdict,
+    pub profile_url: String,
+}
+
+/// A site that failed in the newer scan but not in the older one
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NewError {
+    pub site: String,
+    pub error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<CheckError>,
+}
+
+/// What changed between two scans of the same username
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReportDiff {
+    pub username: String,
+    pub before_generated_at: String,
+    pub after_generated_at: String,
+    pub flipped: Vec<VerdictChange>,
+    /// Sites only present in the newer report
+    pub added: Vec<String>,
+    /// Sites only present in the older report
+    pub removed: Vec<String>,
+    pub new_errors: Vec<NewError>,
+}
+
+impl ReportDiff {
+    pub fn is_empty(&self) -> bool {
+        self.flipped.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.new_errors.is_empty()
+    }
+}
+
+/// Compares two reports site by site, in the order of the newer report
+pub fn diff_reports(before: &Report, after: &Report) -> ReportDiff {
+    let previous: HashMap<&str, &CheckResult> = before
+        .results
+        .iter()
+        .map(|result| (result.site.as_str(), result))
+        .collect();
+    let current: HashMap<&str, &CheckResult> = after
+        .results
+        .iter()
+        .map(|result| (result.site.as_str(), result))
+        .collect();
+
+    let mut diff = ReportDiff {
+        username: after.username.clone(),
+        before_generated_at: before.generated_at.clone(),
+        after_generated_at: after.generated_at.clone(),
+        flipped: Vec::new(),
+        added: Vec::new(),
+        removed: Vec::new(),
+        new_errors: Vec::new(),
+    };
+
+    for result in &after.results {
+        let Some(old) = previous.get(result.site.as_str()) else {
+            diff.added.push(result.site.clone());
+            continue;
+        };
+        if matches!(
+            (old.verdict, result.verdict),
+            (Verdict::Taken, Verdict::Available) | (Verdict::Available, Verdict::Taken)
+        ) {
+            diff.flipped.push(VerdictChange {
+                site: result.site.clone(),
+                before: old.verdict,
+                after: result.verdict,
+                profile_url: result.profile_url.clone(),
+            });
+        }
+        if result.verdict == Verdict::Error && old.verdict != Verdict::Error {
+            diff.new_errors.push(NewError {
+                site: result.site.clone(),
+                error: result.error.clone().unwrap_or_default(),
+                kind: result.error_kind,
+            });
+        }
+    }
+    diff.removed = before
+        .results
+        .iter()
+        .filter(|result| !current.contains_key(result.site.as_str()))
+        .map(|result| result.site.clone())
+        .collect();
+
+    diff
+}
+
+pub fn write_diff_text(file: &mut impl Write, diff: &ReportDiff) -> io::Result<()> {
+    writeln!(file, "Changes for username: {}", diff.username)?;
+    writeln!(
+        file,
+        "From {} to {}",
+        diff.before_generated_at, diff.after_generated_at
+    )?;
+    writeln!(file, "{}", "-".repeat(80))?;
+    if diff.is_empty() {
+        writeln!(file, "No changes")?;
+        return Ok(());
+    }
+    for change in &diff.flipped {
+        writeln!(
+            file,
+            "{}: {} -> {} ({})",
+            change.site, change.before, change.after, change.profile_url
+        )?;
+    }
+    for site in &diff.added {
+        writeln!(file, "Added: {}", site)?;
+    }
+    for site in &diff.removed {
+        writeln!(file, "Removed: {}", site)?;
+    }
+    for error in &diff.new_errors {
+        match error.kind {
+            Some(kind) => writeln!(
+                file,
+                "New error: {} - {}: {}",
+                error.site, kind, error.error
+            )?,
+            None => writeln!(file, "New error: {} - {}", error.site, error.error)?,
+        }
+    }
+    Ok(())
+}
+
+pub fn write_diff_markdown(file: &mut impl Write, diff: &ReportDiff) -> io::Result<()> {
+    writeln!(file, "# Changes for `{}`", diff.username)?;
+    writeln!(file)?;
+    writeln!(
+        file,
+        "From {} to {}",
+        diff.before_generated_at, diff.after_generated_at
+    )?;
+    if diff.is_empty() {
+        writeln!(file)?;
+        writeln!(file, "No changes.")?;
+        return Ok(());
+    }
+    if !diff.flipped.is_empty() {
+        writeln!(file)?;
+        writeln!(file, "## Flipped")?;
+        writeln!(file)?;
+        writeln!(file, "| Site | Before | After | Profile |")?;
+        writeln!(file, "| --- | --- | --- | --- |")?;
+        for change in &diff.flipped {
+            writeln!(
+                file,
+                "| {} | {} | {} | <{}> |",
+                change.site.replace('|', "\\|"),
+                change.before,
+                change.after,
+                change.profile_url
+            )?;
+        }
+    }
+    for (heading, sites) in [
+        ("Added sites", &diff.added),
+        ("Removed sites", &diff.removed),
+    ] {
+        if sites.is_empty() {
+            continue;
+        }
+        writeln!(file)?;
+        writeln!(file, "## {}", heading)?;
+        writeln!(file)?;
+        for site in sites {
+            writeln!(file, "- {}", site)?;
+        }
+    }
+    if !diff.new_errors.is_empty() {
+        writeln!(file)?;
+        writeln!(file, "## New errors")?;
+        writeln!(file)?;
+        for error in &diff.new_errors {
+            match error.kind {
+                Some(kind) => writeln!(file, "- {}: {} ({})", error.site, error.error, kind)?,
+                None => writeln!(file, "- {}: {}", error.site, error.error)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    /// Errors are timeouts
+    fn result(site: &str, verdict: Verdict) -> CheckResult {
+        let failed = verdict == Verdict::Error;
+        CheckResult {
+            error: failed.then(|| "timed out".to_string()),
+            error_kind: failed.then_some(CheckError::Timeout),
+            ..test_util::result(site, verdict)
+        }
+    }
+
+    #[test]
+    fn test_diff_reports() {
+        let before = Report::new(
+            "johndoe",
+            vec![
+                result("GitHub", Verdict::Available),
+                result("GitLab", Verdict::Taken),
+                result("Mastodon", Verdict::Taken),
+                result("MySpace", Verdict::Taken),
+                result("Reddit", Verdict::Error),
+            ],
+        );
+        let after = Report::new(
+            "johndoe",
+            vec![
+                result("GitHub", Verdict::Taken),
+                result("GitLab", Verdict::Available),
+                result("Mastodon", Verdict::Error),
+                result("Reddit", Verdict::Error),
+                result("Bluesky", Verdict::Available),
+            ],
+        );
+
+        let diff = diff_reports(&before, &after);
+        let flipped: Vec<(&str, Verdict, Verdict)> = diff
+            .flipped
+            .iter()
+            .map(|change| (change.site.as_str(), change.before, change.after))
+            .collect();
+        assert_eq!(
+            flipped,
+            [
+                ("GitHub", Verdict::Available, Verdict::Taken),
+                ("GitLab", Verdict::Taken, Verdict::Available),
+            ]
+        );
+        assert_eq!(diff.added, ["Bluesky"]);
+        assert_eq!(diff.removed, ["MySpace"]);
+        assert_eq!(
+            diff.new_errors,
+            [NewError {
+                site: "Mastodon".to_string(),
+                error: "timed out".to_string(),
+                kind: Some(CheckError::Timeout),
+            }]
+        );
+        assert!(diff_reports(&after, &after).is_empty());
+    }
+}
@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::process::ExitStatus;
+use std::str::FromStr;
+
+/// Errors returned by the library outside of per-site checks
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An HTTP request could not be sent or its body could not be read
+    Http(reqwest::Error),
+    /// A download or webhook answered with a non-success status
+    Status {
+        url: String,
+        status: u16,
+    },
+    Io(io::Error),
+    Json(serde_json::Error),
+    Csv(csv::Error),
+    Template(askama::Error),
+    /// The history database could not be opened, read or written
+    History(rusqlite::Error),
+    /// A payload could not be signed
+    Ssl(openssl::error::ErrorStack),
+    /// The report format is not available for this kind of report
+    UnsupportedFormat(String),
+    /// A notification command exited unsuccessfully
+    Command(ExitStatus),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "HTTP request failed: {}", e),
+            Error::Status { url, status } => write!(f, "{} answered HTTP {}", url, status),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Json(e) => write!(f, "Invalid JSON: {}", e),
+            Error::Csv(e) => write!(f, "CSV error: {}", e),
+            Error::Template(e) => write!(f, "Failed to render template: {}", e),
+            Error::History(e) => write!(f, "History database error: {}", e),
+            Error::Ssl(e) => write!(f, "Signing failed: {}", e),
+            Error::UnsupportedFormat(message) => f.write_str(message),
+            Error::Command(status) => write!(f, "Command exited with {}", status),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Csv(e) => Some(e),
+            Error::Template(e) => Some(e),
+            Error::History(e) => Some(e),
+            Error::Ssl(e) => Some(e),
+            Error::Status { .. } | Error::UnsupportedFormat(_) | Error::Command(_) => None,
+        }
+    }
+}
+
+macro_rules! from_error {
+    ($($source:ty => $variant:ident),* $(,)?) => {
+        $(impl From<$source> for Error {
+            fn from(e: $source) -> Self {
+                Error::$variant(e)
+            }
+        })*
+    };
+}
+
+from_error! {
+    reqwest::Error => Http,
+    io::Error => Io,
+    serde_json::Error => Json,
+    csv::Error => Csv,
+    askama::Error => Template,
+    rusqlite::Error => History,
+    openssl::error::ErrorStack => Ssl,
+}
+
+/// Why a single site check failed, kept in `CheckResult` so that failures
+/// can be grouped by cause
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckError {
+    /// No response arrived in time
+    Timeout,
+    /// The host name could not be resolved
+    Dns,
+    /// The TCP connection was refused, reset or unreachable
+    Connect,
+    /// The TLS handshake failed, e.g. on an invalid certificate
+    Tls,
+    /// The proxy could not be reached or no healthy proxy was left
+    Proxy,
+    /// The response body could not be read or decoded
+    Decode,
+    /// The site data describes a request that cannot be built
+    Request,
+    /// Anything else
+    Other,
+}
+
+impl CheckError {
+    /// Every kind, in the order used for summaries
+    pub const ALL: [CheckError; 8] = [
+        CheckError::Timeout,
+        CheckError::Dns,
+        CheckError::Connect,
+        CheckError::Tls,
+        CheckError::Proxy,
+        CheckError::Decode,
+        CheckError::Request,
+        CheckError::Other,
+    ];
+
+    /// Human readable label used in reports and the web UI
+    pub fn label(&self) -> &'static str {
+        match self {
+            CheckError::Timeout => "Timeout",
+            CheckError::Dns => "DNS failure",
+            CheckError::Connect => "Connection failed",
+            CheckError::Tls => "TLS failure",
+            CheckError::Proxy => "Proxy failure",
+            CheckError::Decode => "Unreadable response",
+            CheckError::Request => "Invalid request",
+            CheckError::Other => "Other error",
+        }
+    }
+
+    /// Machine name, as serialized
+    pub fn key(&self) -> &'static str {
+        match self {
+            CheckError::Timeout => "timeout",
+            CheckError::Dns => "dns",
+            CheckError::Connect => "connect",
+            CheckError::Tls => "tls",
+            CheckError::Proxy => "proxy",
+            CheckError::Decode => "decode",
+            CheckError::Request => "request",
+            CheckError::Other => "other",
+        }
+    }
+
+    /// Classifies a failed request; connection failures through a proxy are
+    /// blamed on the proxy since the site was never contacted directly, TLS
+    /// failures are the site's own even through a tunnel.
+    ///
+    /// TLS and socket failures are recognized by their typed sources. The
+    /// resolver error has no public type, so DNS detection falls back to the
+    /// error text and is best-effort: an unrecognized resolver failure ends
+    /// up as `Connect`.
+    pub fn classify(error: &reqwest::Error, via_proxy: bool) -> Self {
+        if error.is_timeout() {
+            CheckError::Timeout
+        } else if error.is_builder() {
+            CheckError::Request
+        } else if error.is_decode() || error.is_body() {
+            CheckError::Decode
+        } else if error.is_connect() {
+            if sources(error).any(is_tls_error) {
+                CheckError::Tls
+            } else if via_proxy {
+                CheckError::Proxy
+            } else if sources(error).any(is_socket_error) {
+                CheckError::Connect
+            } else if sources(error).any(|e| e.to_string().starts_with("dns error")) {
+                CheckError::Dns
+            } else {
+                CheckError::Connect
+            }
+        } else {
+            CheckError::Other
+        }
+    }
+}
+
+/// The chain of errors that caused `error`, outermost first
+fn sources(error: &reqwest::Error) -> impl Iterator<Item = &(dyn StdError + 'static)> {
+    std::iter::successors(error.source(), |&e| e.source())
+}
+
+/// Errors of the TLS backend, or of OpenSSL underneath it
+fn is_tls_error(error: &(dyn StdError + 'static)) -> bool {
+    error.is::<native_tls::Error>()
+        || error.is::<openssl::error::ErrorStack>()
+        || error.is::<openssl::ssl::Error>()
+}
+
+/// Operating system errors from connecting the socket itself
+fn is_socket_error(error: &(dyn StdError + 'static)) -> bool {
+    error.downcast_ref::<io::Error>().is_some_and(|e| {
+        matches!(
+            e.kind(),
+            io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::HostUnreachable
+                | io::ErrorKind::NetworkUnreachable
+                | io::ErrorKind::AddrNotAvailable
+        )
+    })
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl FromStr for CheckError {
+    type Err = String;
+
+    /// Parses the machine name returned by `key`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CheckError::ALL
+            .into_iter()
+            .find(|kind| kind.key() == s)
+            .ok_or_else(|| format!("Unknown error kind: {}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::serve;
+    use poem::{Route, get, handler};
+
+    #[handler]
+    fn ok() -> &'static str {
+        "ok"
+    }
+
+    #[tokio::test]
+    async fn test_classify() {
+        let client = reqwest::Client::new();
+        let kind = |url: String, via_proxy| {
+            let client = client.clone();
+            async move {
+                let error = client.get(url).send().await.unwrap_err();
+                CheckError::classify(&error, via_proxy)
+            }
+        };
+
+        assert_eq!(
+            kind("http://127.0.0.1:1/".to_string(), false).await,
+            CheckError::Connect
+        );
+        assert_eq!(
+            kind("http://127.0.0.1:1/".to_string(), true).await,
+            CheckError::Proxy
+        );
+        assert_eq!(
+            kind("http://firststep-name.invalid/".to_string(), false).await,
+            CheckError::Dns
+        );
+        let base = serve(Route::new().at("/", get(ok))).await;
+        let https = base.replacen("http://", "https://", 1);
+        assert_eq!(kind(https, false).await, CheckError::Tls);
+    }
+}
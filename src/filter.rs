@@ -0,0 +1,264 @@
+use crate::SiteData;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Category of adult sites, only checked on explicit opt-in
+pub const NSFW_CATEGORY: &str = "xx NSFW xx";
+
+/// Selects which sites of the data file take part in a scan
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SiteFilter {
+    /// Only check sites in these categories; empty means every category
+    pub categories: Vec<String>,
+    /// Never check sites in these categories
+    pub exclude_categories: Vec<String>,
+    /// Only check sites whose name matches one of these patterns, globs
+    /// (`*`, `?`) or `/regex/`
+    pub sites: Vec<String>,
+    /// Never check sites whose name matches one of these patterns
+    pub exclude_sites: Vec<String>,
+    /// Include the NSFW category, which is also included when listed in `categories`
+    pub nsfw: bool,
+    /// Leave out sites that declare a bot protection in the data file
+    pub skip_protected: bool,
+}
+
+impl SiteFilter {
+    /// Whether `site` takes part in a scan using this filter; compiles the
+    /// name patterns on every call, see `matcher` for many sites
+    pub fn matches(&self, site: &SiteData) -> bool {
+        self.matcher().matches(site)
+    }
+
+    /// The filter with its name patterns compiled once
+    pub fn matcher(&self) -> SiteMatcher<'_> {
+        SiteMatcher {
+            filter: self,
+            sites: NamePattern::compile_all(&self.sites),
+            exclude_sites: NamePattern::compile_all(&self.exclude_sites),
+        }
+    }
+
+    /// Requested categories that are not in `known`, usually a typo
+    pub fn unknown_categories<'a>(&'a self, known: &[String]) -> Vec<&'a str> {
+        self.categories
+            .iter()
+            .chain(&self.exclude_categories)
+            .filter(|category| !known.iter().any(|k| k.eq_ignore_ascii_case(category)))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// The sites of `sites_data` selected by this filter, in data file order
+    pub fn apply<'a>(&self, sites_data: &'a [SiteData]) -> Vec<&'a SiteData> {
+        let matcher = self.matcher();
+        sites_data
+            .iter()
+            .filter(|site| matcher.matches(site))
+            .collect()
+    }
+}
+
+/// A `SiteFilter` ready to test many sites
+#[derive(Debug)]
+pub struct SiteMatcher<'a> {
+    filter: &'a SiteFilter,
+    sites: Vec<NamePattern>,
+    exclude_sites: Vec<NamePattern>,
+}
+
+impl SiteMatcher<'_> {
+    /// Whether `site` takes part in a scan using the filter
+    pub fn matches(&self, site: &SiteData) -> bool {
+        let filter = self.filter;
+        let in_category = |categories: &[String]| {
+            categories
+                .iter()
+                .any(|category| category.eq_ignore_ascii_case(&site.cat))
+        };
+
+        if site.cat.eq_ignore_ascii_case(NSFW_CATEGORY)
+            && !filter.nsfw
+            && !in_category(&filter.categories)
+        {
+            return false;
+        }
+        if filter.skip_protected && site.is_protected() {
+            return false;
+        }
+
+        (filter.categories.is_empty() || in_category(&filter.categories))
+            && !in_category(&filter.exclude_categories)
+            && (self.sites.is_empty() || NamePattern::any_matches(&self.sites, &site.name))
+            && !NamePattern::any_matches(&self.exclude_sites, &site.name)
+    }
+}
+
+/// A site name pattern: `/regex/` searched anywhere in the name, or a glob
+/// that must match all of it; both ignore case
+#[derive(Debug, Clone)]
+pub struct NamePattern(PatternKind);
+
+#[derive(Debug, Clone)]
+enum PatternKind {
+    Glob(String),
+    Regex(Regex),
+    /// A regex that failed to compile, matching no name
+    Invalid,
+}
+
+impl NamePattern {
+    /// Parses `pattern`, failing only on an invalid `/regex/`
+    pub fn parse(pattern: &str) -> Result<Self, regex::Error> {
+        let kind = match pattern
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            Some(regex) => {
+                PatternKind::Regex(RegexBuilder::new(regex).case_insensitive(true).build()?)
+            }
+            None => PatternKind::Glob(pattern.to_string()),
+        };
+        Ok(NamePattern(kind))
+    }
+
+    /// Parses every pattern; an invalid regex matches nothing rather than
+    /// being dropped, which would widen an include list
+    pub fn compile_all(patterns: &[String]) -> Vec<NamePattern> {
+        patterns
+            .iter()
+            .map(|pattern| NamePattern::parse(pattern).unwrap_or(NamePattern(PatternKind::Invalid)))
+            .collect()
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match &self.0 {
+            PatternKind::Glob(glob) => glob_matches(glob, name),
+            PatternKind::Regex(regex) => regex.is_match(name),
+            PatternKind::Invalid => false,
+        }
+    }
+
+    /// Whether any of `patterns` matches `name`
+    pub fn any_matches(patterns: &[NamePattern], name: &str) -> bool {
+        patterns.iter().any(|pattern| pattern.matches(name))
+    }
+}
+
+/// Case-insensitive glob match supporting `*` (any run) and `?` (one character)
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` swallow one more character and try again
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_site;
+
+    fn site(name: &str, cat: &str) -> SiteData {
+        let mut site = test_site(format!("https://{}.example/{{account}}", name));
+        site.name = name.to_string();
+        site.cat = cat.to_string();
+        site
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("git*", "GitHub"));
+        assert!(glob_matches("*fandom*", "Fandom (community)"));
+        assert!(glob_matches("b?tchute", "Bitchute"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("github", "GitHub Gist"));
+        assert!(!glob_matches("?", ""));
+    }
+
+    #[test]
+    fn test_name_pattern() {
+        let matches = |pattern: &str, name| NamePattern::parse(pattern).unwrap().matches(name);
+        assert!(matches("/^git(hub|lab)$/", "GitLab"));
+        assert!(!matches("/^git(hub|lab)$/", "GitHub Gist"));
+        assert!(matches("/gist/", "GitHub Gist"));
+        assert!(matches("git*", "GitHub"));
+        assert!(!matches("/", "GitHub"), "a lone slash is a glob");
+        assert!(NamePattern::parse("/git(/").is_err());
+
+        let invalid = NamePattern::compile_all(&["/git(/".to_string()]);
+        assert!(!NamePattern::any_matches(&invalid, "GitHub"));
+    }
+
+    #[test]
+    fn test_site_filter() {
+        let sites = [
+            site("GitHub", "coding"),
+            site("GitLab", "coding"),
+            site("Instagram", "social"),
+            site("Adult", NSFW_CATEGORY),
+        ];
+        let names = |filter: &SiteFilter| -> Vec<String> {
+            filter
+                .apply(&sites)
+                .iter()
+                .map(|s| s.name.clone())
+                .collect()
+        };
+
+        assert_eq!(
+            names(&SiteFilter::default()),
+            ["GitHub", "GitLab", "Instagram"]
+        );
+        let nsfw = SiteFilter {
+            nsfw: true,
+            ..SiteFilter::default()
+        };
+        assert_eq!(names(&nsfw).len(), 4);
+
+        let coding = SiteFilter {
+            categories: vec!["Coding".to_string()],
+            exclude_sites: vec!["*lab".to_string()],
+            ..SiteFilter::default()
+        };
+        assert_eq!(names(&coding), ["GitHub"]);
+
+        let explicit = SiteFilter {
+            categories: vec![NSFW_CATEGORY.to_string()],
+            ..SiteFilter::default()
+        };
+        assert_eq!(names(&explicit), ["Adult"]);
+
+        let regex = SiteFilter {
+            sites: vec!["/^git/".to_string()],
+            exclude_sites: vec!["/hub$/".to_string()],
+            ..SiteFilter::default()
+        };
+        assert_eq!(names(&regex), ["GitLab"]);
+
+        let selected = SiteFilter {
+            sites: vec!["git*".to_string(), "instagram".to_string()],
+            exclude_categories: vec!["social".to_string()],
+            ..SiteFilter::default()
+        };
+        assert_eq!(names(&selected), ["GitHub", "GitLab"]);
+    }
+}
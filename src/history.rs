@@ -0,0 +1,199 @@
+use crate::filter::NamePattern;
+use crate::{CheckResult, Verdict};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS checks (
+        id INTEGER PRIMARY KEY,
+        username TEXT NOT NULL,
+        site TEXT NOT NULL,
+        verdict TEXT NOT NULL,
+        http_status INTEGER,
+        latency_ms INTEGER,
+        checked_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS checks_by_username
+        ON checks (username, site, checked_at);
+";
+
+/// One recorded check of a username on a site
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub site: String,
+    pub verdict: Verdict,
+    pub http_status: Option<u16>,
+    pub latency_ms: Option<u64>,
+    /// RFC 3339 timestamp in UTC
+    pub checked_at: String,
+}
+
+/// Every recorded check of a username on one site, oldest first
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SiteTimeline {
+    pub site: String,
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl SiteTimeline {
+    /// Entries whose verdict differs from the one before, the first included
+    pub fn changes(&self) -> Vec<&HistoryEntry> {
+        let mut previous = None;
+        self.entries
+            .iter()
+            .filter(|entry| previous.replace(entry.verdict) != Some(entry.verdict))
+            .collect()
+    }
+}
+
+/// SQLite file recording the outcome of every scan
+pub struct HistoryStore {
+    conn: Mutex<Connection>,
+}
+
+impl HistoryStore {
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(HistoryStore {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Records the results of one scan of `username`
+    pub fn record(
+        &self,
+        username: &str,
+        results: &[CheckResult],
+        checked_at: DateTime<Utc>,
+    ) -> rusqlite::Result<()> {
+        let checked_at = checked_at.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO checks (username, site, verdict, http_status, latency_ms, checked_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for result in results {
+                insert.execute(params![
+                    username,
+                    result.site,
+                    result.verdict.key(),
+                    result.http_status,
+                    result.latency_ms,
+                    checked_at,
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Per-site timelines of `username`, limited to sites whose name matches
+    /// one of the `sites` globs or `/regex/` patterns unless that is empty
+    pub fn timeline(
+        &self,
+        username: &str,
+        sites: &[String],
+    ) -> rusqlite::Result<Vec<SiteTimeline>> {
+        let conn = self.conn.lock().unwrap();
+        let mut query = conn.prepare(
+            "SELECT site, verdict, http_status, latency_ms, checked_at FROM checks
+             WHERE username = ?1 ORDER BY site, checked_at, id",
+        )?;
+        let rows = query.query_map([username], |row| {
+            let verdict: String = row.get(1)?;
+            Ok(HistoryEntry {
+                site: row.get(0)?,
+                verdict: verdict.parse().unwrap_or(Verdict::Inconclusive),
+                http_status: row.get(2)?,
+                latency_ms: row.get(3)?,
+                checked_at: row.get(4)?,
+            })
+        })?;
+
+        let patterns = NamePattern::compile_all(sites);
+        let mut timelines: Vec<SiteTimeline> = Vec::new();
+        for entry in rows {
+            let entry = entry?;
+            if !patterns.is_empty() && !NamePattern::any_matches(&patterns, &entry.site) {
+                continue;
+            }
+            match timelines.last_mut() {
+                Some(timeline) if timeline.site == entry.site => timeline.entries.push(entry),
+                _ => timelines.push(SiteTimeline {
+                    site: entry.site.clone(),
+                    entries: vec![entry],
+                }),
+            }
+        }
+        Ok(timelines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::result;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_record_and_timeline() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let day = |d| Utc.with_ymd_and_hms(2025, 3, d, 12, 0, 0).unwrap();
+        store
+            .record(
+                "johndoe",
+                &[
+                    result("GitHub", Verdict::Taken),
+                    result("GitLab", Verdict::Taken),
+                ],
+                day(1),
+            )
+            .unwrap();
+        store
+            .record("johndoe", &[result("GitHub", Verdict::Taken)], day(2))
+            .unwrap();
+        store
+            .record("johndoe", &[result("GitHub", Verdict::Available)], day(3))
+            .unwrap();
+        store
+            .record("janedoe", &[result("GitHub", Verdict::Available)], day(3))
+            .unwrap();
+
+        let timelines = store.timeline("johndoe", &[]).unwrap();
+        let sites: Vec<&str> = timelines.iter().map(|t| t.site.as_str()).collect();
+        assert_eq!(sites, ["GitHub", "GitLab"]);
+        assert_eq!(timelines[0].entries.len(), 3);
+        assert_eq!(timelines[0].entries[0].http_status, Some(200));
+        assert_eq!(timelines[0].entries[0].latency_ms, Some(42));
+
+        let changes: Vec<(Verdict, &str)> = timelines[0]
+            .changes()
+            .into_iter()
+            .map(|entry| (entry.verdict, entry.checked_at.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (Verdict::Taken, "2025-03-01T12:00:00Z"),
+                (Verdict::Available, "2025-03-03T12:00:00Z"),
+            ]
+        );
+
+        let gitlab = store.timeline("johndoe", &["gitl*".to_string()]).unwrap();
+        assert_eq!(gitlab.len(), 1);
+        assert_eq!(gitlab[0].site, "GitLab");
+        assert!(store.timeline("nobody", &[]).unwrap().is_empty());
+    }
+}
@@ -1,43 +1,475 @@
 use colored::*;
-use futures_util::sink::SinkExt;
-use poem::web::websocket::{Message, WebSocketStream};
-use reqwest::Client;
+use futures_util::stream::{self, StreamExt};
+use regex::Regex;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, Method, RequestBuilder};
 use serde::{Deserialize, Serialize};
-use std::error::Error;
-use std::fs::File;
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::Mutex;
-use tokio::time::sleep;
+use std::time::{Duration, Instant};
 use url::Url;
 
+pub mod batch;
+pub mod checker;
+pub mod diff;
+pub mod error;
+pub mod filter;
+pub mod history;
+pub mod protection;
+pub mod proxy;
+pub mod ratelimit;
+pub mod report;
+pub mod retry;
+pub mod selftest;
+pub mod sink;
+pub mod variants;
+pub mod watch;
+pub mod webhook;
+
+#[cfg(test)]
+mod test_util;
+
+pub use batch::{BatchReport, CandidateSummary};
+pub use checker::{Checker, CheckerBuilder};
+pub use error::{CheckError, Error};
+pub use filter::SiteFilter;
+pub use history::HistoryStore;
+pub use proxy::{ProxyPool, Rotation};
+pub use ratelimit::{HostRateLimiter, RateLimitPolicy};
+pub use report::{
+    Report, ReportDestination, ReportFormat, save_csv_report, save_html_report, save_json_report,
+    save_markdown_report, save_report, save_txt_report, write_report, write_txt_report,
+};
+pub use retry::RetryPolicy;
+pub use sink::{ConsoleSink, NdjsonSink, NullSink, ProgressSink, StderrSink, WebSocketSink};
+pub use webhook::CompletionWebhooks;
+
 const DEFAULT_DATA_URL: &str =
     "https://raw.githubusercontent.com/WebBreacher/WhatsMyName/main/wmn-data.json";
 
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
+
+/// Outcome of checking a username against a single site
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// The `e_code` and `e_string` rule matched, the account exists
+    Taken,
+    /// The `m_code` and `m_string` rule matched, the account does not exist
+    Available,
+    /// The response matched neither rule (rate limits, challenges, layout changes)
+    Inconclusive,
+    /// The request failed before a response could be evaluated
+    Error,
+    /// The username cannot exist on the site, so it was not probed
+    Invalid,
+    /// The site answered with a bot-protection challenge instead of the page
+    Blocked,
+    /// A random control username was reported as taken too, so the site's
+    /// "exists" answer does not depend on the username
+    Indeterminate,
+}
+
+impl Verdict {
+    /// Every verdict, in the order used for summaries
+    pub const ALL: [Verdict; 7] = [
+        Verdict::Taken,
+        Verdict::Available,
+        Verdict::Indeterminate,
+        Verdict::Inconclusive,
+        Verdict::Blocked,
+        Verdict::Error,
+        Verdict::Invalid,
+    ];
+
+    /// Human readable label used in reports and the web UI
+    pub fn label(&self) -> &'static str {
+        match self {
+            Verdict::Taken => "Taken",
+            Verdict::Available => "Available",
+            Verdict::Inconclusive => "Inconclusive",
+            Verdict::Error => "Error",
+            Verdict::Invalid => "Invalid for this site",
+            Verdict::Blocked => "Blocked by protection",
+            Verdict::Indeterminate => "Indeterminate",
+        }
+    }
+
+    /// Machine name, as serialized and used for CSS classes
+    pub fn key(&self) -> &'static str {
+        match self {
+            Verdict::Taken => "taken",
+            Verdict::Available => "available",
+            Verdict::Inconclusive => "inconclusive",
+            Verdict::Error => "error",
+            Verdict::Invalid => "invalid",
+            Verdict::Blocked => "blocked",
+            Verdict::Indeterminate => "indeterminate",
+        }
+    }
+
+    /// Terminal color used when printing the verdict
+    pub fn color(&self) -> Color {
+        match self {
+            Verdict::Taken => Color::Red,
+            Verdict::Available => Color::Green,
+            Verdict::Inconclusive => Color::Yellow,
+            Verdict::Error => Color::Magenta,
+            Verdict::Invalid => Color::Cyan,
+            Verdict::Blocked => Color::BrightYellow,
+            Verdict::Indeterminate => Color::BrightBlue,
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl FromStr for Verdict {
+    type Err = String;
+
+    /// Parses the machine name returned by `key`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Verdict::ALL
+            .into_iter()
+            .find(|verdict| verdict.key() == s)
+            .ok_or_else(|| format!("Unknown verdict: {}", s))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProgressUpdate {
     site: String,
+    verdict: Verdict,
     status: String,
-    url: String,
+    check_url: String,
+    profile_url: String,
     logo_url: String,
     error: Option<String>,
-    is_taken: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error_kind: Option<CheckError>,
     completed: usize,
     total: usize,
 }
 
+impl ProgressUpdate {
+    /// Update sent for `result`, the `completed`-th of `total` checks
+    pub fn new(result: &CheckResult, completed: usize, total: usize) -> Self {
+        ProgressUpdate {
+            site: result.site.clone(),
+            verdict: result.verdict,
+            status: result.verdict.to_string(),
+            check_url: result.check_url.clone(),
+            profile_url: result.profile_url.clone(),
+            logo_url: result.logo_url.clone(),
+            error: result.error.clone(),
+            error_kind: result.error_kind,
+            completed,
+            total,
+        }
+    }
+
+    pub fn site(&self) -> &str {
+        &self.site
+    }
+
+    pub fn verdict(&self) -> Verdict {
+        self.verdict
+    }
+
+    pub fn check_url(&self) -> &str {
+        &self.check_url
+    }
+
+    pub fn profile_url(&self) -> &str {
+        &self.profile_url
+    }
+
+    pub fn logo_url(&self) -> &str {
+        &self.logo_url
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn error_kind(&self) -> Option<CheckError> {
+        self.error_kind
+    }
+
+    /// Number of sites checked so far, this one included
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
+
+/// A site's `username_pattern`, compiled once when the site data is loaded
+/// so that a bad pattern fails the load instead of every check
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct UsernamePattern {
+    pattern: String,
+    regex: Regex,
+}
+
+impl UsernamePattern {
+    /// Compiles `pattern`, anchored so that it must match the whole username
+    pub fn new(pattern: impl Into<String>) -> Result<Self, regex::Error> {
+        let pattern = pattern.into();
+        let regex = Regex::new(&format!("^(?:{})$", pattern))?;
+        Ok(UsernamePattern { pattern, regex })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_match(&self, account: &str) -> bool {
+        self.regex.is_match(account)
+    }
+}
+
+impl TryFrom<String> for UsernamePattern {
+    type Error = regex::Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        UsernamePattern::new(pattern)
+    }
+}
+
+impl From<UsernamePattern> for String {
+    fn from(pattern: UsernamePattern) -> Self {
+        pattern.pattern
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SiteData {
     name: String,
     uri_check: String,
+    /// Profile page shown to humans when `uri_check` is an API endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uri_pretty: Option<String>,
     e_code: u16,
     e_string: String,
     m_string: String,
     m_code: u16,
     known: Vec<String>,
     cat: String,
+    /// HTTP method to use; defaults to POST when `post_body` is set, GET otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    method: Option<String>,
+    /// Request body template, `{account}` is replaced with the username
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    post_body: Option<String>,
+    /// Extra request headers sent with the check
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    headers: HashMap<String, String>,
+    /// Characters removed from the username before it is substituted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    strip_bad_char: Option<String>,
+    /// Longest username the site accepts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    username_max_length: Option<usize>,
+    /// Regex the whole username must match to be valid on the site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    username_pattern: Option<UsernamePattern>,
+    /// Bot protection the site is known to use, e.g. `cloudflare`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    protection: Vec<String>,
+}
+
+impl SiteData {
+    /// A site where `e_code` and a body containing `e_string` mean the
+    /// account exists, and `m_code` with `m_string` mean it does not.
+    /// `{account}` in `uri_check` is replaced with the username.
+    pub fn new(
+        name: impl Into<String>,
+        uri_check: impl Into<String>,
+        e_code: u16,
+        e_string: impl Into<String>,
+        m_code: u16,
+        m_string: impl Into<String>,
+    ) -> Self {
+        SiteData {
+            name: name.into(),
+            uri_check: uri_check.into(),
+            uri_pretty: None,
+            e_code,
+            e_string: e_string.into(),
+            m_string: m_string.into(),
+            m_code,
+            known: Vec::new(),
+            cat: String::new(),
+            method: None,
+            post_body: None,
+            headers: HashMap::new(),
+            strip_bad_char: None,
+            username_max_length: None,
+            username_pattern: None,
+            protection: Vec::new(),
+        }
+    }
+
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.cat = category.into();
+        self
+    }
+
+    /// Profile page shown to humans when `uri_check` is an API endpoint
+    pub fn with_uri_pretty(mut self, uri_pretty: impl Into<String>) -> Self {
+        self.uri_pretty = Some(uri_pretty.into());
+        self
+    }
+
+    /// Usernames known to exist, used by the self-test
+    pub fn with_known(mut self, known: Vec<String>) -> Self {
+        self.known = known;
+        self
+    }
+
+    pub fn with_method(mut self, method: impl Into<String>) -> Self {
+        self.method = Some(method.into());
+        self
+    }
+
+    /// Request body template, `{account}` is replaced with the username
+    pub fn with_post_body(mut self, post_body: impl Into<String>) -> Self {
+        self.post_body = Some(post_body.into());
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_strip_bad_char(mut self, chars: impl Into<String>) -> Self {
+        self.strip_bad_char = Some(chars.into());
+        self
+    }
+
+    pub fn with_username_max_length(mut self, max: usize) -> Self {
+        self.username_max_length = Some(max);
+        self
+    }
+
+    /// Regex the whole username must match to be valid on the site
+    pub fn with_username_pattern(mut self, pattern: UsernamePattern) -> Self {
+        self.username_pattern = Some(pattern);
+        self
+    }
+
+    pub fn with_protection(mut self, protection: Vec<String>) -> Self {
+        self.protection = protection;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn uri_check(&self) -> &str {
+        &self.uri_check
+    }
+
+    pub fn uri_pretty(&self) -> Option<&str> {
+        self.uri_pretty.as_deref()
+    }
+
+    pub fn e_code(&self) -> u16 {
+        self.e_code
+    }
+
+    pub fn e_string(&self) -> &str {
+        &self.e_string
+    }
+
+    pub fn m_code(&self) -> u16 {
+        self.m_code
+    }
+
+    pub fn m_string(&self) -> &str {
+        &self.m_string
+    }
+
+    pub fn known(&self) -> &[String] {
+        &self.known
+    }
+
+    pub fn category(&self) -> &str {
+        &self.cat
+    }
+
+    pub fn method(&self) -> Option<&str> {
+        self.method.as_deref()
+    }
+
+    pub fn post_body(&self) -> Option<&str> {
+        self.post_body.as_deref()
+    }
+
+    pub fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
+    pub fn strip_bad_char(&self) -> Option<&str> {
+        self.strip_bad_char.as_deref()
+    }
+
+    pub fn username_max_length(&self) -> Option<usize> {
+        self.username_max_length
+    }
+
+    pub fn username_pattern(&self) -> Option<&str> {
+        self.username_pattern.as_ref().map(UsernamePattern::as_str)
+    }
+
+    pub fn protection(&self) -> &[String] {
+        &self.protection
+    }
+
+    /// Whether the site declares a bot protection in the data file
+    pub fn is_protected(&self) -> bool {
+        !self.protection.is_empty()
+    }
+
+    /// The username as the site spells it, with `strip_bad_char` removed
+    pub fn account_for(&self, username: &str) -> String {
+        match &self.strip_bad_char {
+            Some(bad) => username.chars().filter(|c| !bad.contains(*c)).collect(),
+            None => username.to_string(),
+        }
+    }
+
+    /// Checks the site's username constraints, returning the reason on failure
+    pub fn validate_account(&self, account: &str) -> Result<(), String> {
+        if account.is_empty() {
+            return Err("Username is empty after removing unsupported characters".to_string());
+        }
+        if let Some(max) = self.username_max_length
+            && account.chars().count() > max
+        {
+            return Err(format!("Username is longer than {} characters", max));
+        }
+        if let Some(pattern) = &self.username_pattern
+            && !pattern.is_match(account)
+        {
+            return Err(format!("Username does not match {}", pattern.as_str()));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -48,20 +480,129 @@ pub struct SitesFile {
     pub sites: Vec<SiteData>,
 }
 
+impl SitesFile {
+    /// A data file holding `sites`, declaring the categories they use
+    pub fn new(sites: Vec<SiteData>) -> Self {
+        let mut categories: Vec<String> = sites.iter().map(|site| site.cat.clone()).collect();
+        categories.sort();
+        categories.dedup();
+        SitesFile {
+            license: Vec::new(),
+            authors: Vec::new(),
+            categories,
+            sites,
+        }
+    }
+
+    /// Reads a WhatsMyName style data file
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, Error> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn license(&self) -> &[String] {
+        &self.license
+    }
+
+    pub fn authors(&self) -> &[String] {
+        &self.authors
+    }
+
+    /// Categories declared by the data file
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
+
+    pub fn sites(&self) -> &[SiteData] {
+        &self.sites
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CheckResult {
     site: String,
-    status: String,
-    url: String,
+    /// Category of the site in the data file
+    #[serde(default)]
+    category: String,
+    verdict: Verdict,
+    /// URL the check request was sent to, often an API endpoint
+    check_url: String,
+    /// Human-facing profile page, `uri_pretty` when the site defines one
+    profile_url: String,
     logo_url: String,
     error: Option<String>,
+    /// Cause of the failure when the verdict is `Error`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error_kind: Option<CheckError>,
+    /// Number of requests sent, more than one when transient failures were retried
+    #[serde(default = "default_attempts")]
+    attempts: u32,
+    /// Verdict for the random control username, when control checks are enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    control_verdict: Option<Verdict>,
+    /// Status code of the last response, if any arrived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    http_status: Option<u16>,
+    /// Time from sending the last request to reading its body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Report {
-    username: String,
-    generated_at: String,
-    results: Vec<CheckResult>,
+fn default_attempts() -> u32 {
+    1
+}
+
+impl CheckResult {
+    pub fn site(&self) -> &str {
+        &self.site
+    }
+
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+
+    pub fn verdict(&self) -> Verdict {
+        self.verdict
+    }
+
+    /// URL the check request was sent to, often an API endpoint
+    pub fn check_url(&self) -> &str {
+        &self.check_url
+    }
+
+    pub fn profile_url(&self) -> &str {
+        &self.profile_url
+    }
+
+    pub fn logo_url(&self) -> &str {
+        &self.logo_url
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn error_kind(&self) -> Option<CheckError> {
+        self.error_kind
+    }
+
+    /// Number of requests sent, retries included
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn control_verdict(&self) -> Option<Verdict> {
+        self.control_verdict
+    }
+
+    /// Status code of the last response, if any arrived
+    pub fn http_status(&self) -> Option<u16> {
+        self.http_status
+    }
+
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency_ms.map(Duration::from_millis)
+    }
 }
 
 pub fn get_site_logo(domain_name: &str) -> String {
@@ -76,27 +617,58 @@ pub fn get_site_logo(domain_name: &str) -> String {
 }
 
 pub fn extract_domain(url_str: &str) -> Option<String> {
-    if let Ok(url) = Url::parse(url_str) {
-        if let Some(host) = url.host_str() {
-            // Get the base domain (example.com from subdomain.example.com)
-            let parts: Vec<&str> = host.split('.').collect();
-            if parts.len() >= 2 {
-                // For most domains, return the last two parts
-                return Some(format!(
-                    "{}.{}",
-                    parts[parts.len() - 2],
-                    parts[parts.len() - 1]
-                ));
-            } else {
-                return Some(host.to_string());
-            }
+    if let Ok(url) = Url::parse(url_str)
+        && let Some(host) = url.host_str()
+    {
+        // Get the base domain (example.com from subdomain.example.com)
+        let parts: Vec<&str> = host.split('.').collect();
+        if parts.len() >= 2 {
+            // For most domains, return the last two parts
+            return Some(format!(
+                "{}.{}",
+                parts[parts.len() - 2],
+                parts[parts.len() - 1]
+            ));
+        } else {
+            return Some(host.to_string());
         }
     }
     None
 }
 
-pub async fn download_sites_data(client: &Client, output_file: &str) -> Result<(), Box<dyn Error>> {
-    println!("Downloading sites data from {}...", DEFAULT_DATA_URL);
+/// Host name the rate limiter keys `uri` on; the full host rather than the
+/// last two labels, so that sites under e.g. `.com.br` do not share a bucket
+fn rate_limit_host(uri: &str) -> String {
+    Url::parse(uri)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// A random, high-entropy username that almost certainly exists nowhere
+pub fn random_username() -> String {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    let tail: String = (0..13)
+        .map(|_| ALPHABET[rand::random_range(0..ALPHABET.len())] as char)
+        .collect();
+    // Start with a letter, many sites reject usernames with a leading digit
+    format!("{}{}", ALPHABET[rand::random_range(0..26)] as char, tail)
+}
+
+/// Builds an HTTP client with reasonable defaults, optionally routed through
+/// an `http://`, `https://` or `socks5://` proxy
+pub fn build_client(proxy: Option<&str>) -> reqwest::Result<Client> {
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(Duration::from_secs(30));
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    builder.build()
+}
+
+pub async fn download_sites_data(client: &Client, output_file: &str) -> Result<(), Error> {
+    eprintln!("Downloading sites data from {}...", DEFAULT_DATA_URL);
 
     let response = client.get(DEFAULT_DATA_URL).send().await?;
 
@@ -109,246 +681,709 @@ pub async fn download_sites_data(client: &Client, output_file: &str) -> Result<(
             .open(output_file)?;
 
         file.write_all(data.as_bytes())?;
-        println!("Successfully downloaded sites data to {}", output_file);
+        eprintln!("Successfully downloaded sites data to {}", output_file);
         Ok(())
     } else {
-        Err(format!("Failed to download data: HTTP {}", response.status()).into())
+        Err(Error::Status {
+            url: DEFAULT_DATA_URL.to_string(),
+            status: response.status().as_u16(),
+        })
     }
 }
 
-use futures_util::stream::SplitSink;
+/// Tuning knobs for a username scan
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    /// Number of requests kept in flight at any time
+    pub concurrency: usize,
+    /// Emit results in data file order instead of completion order
+    pub ordered: bool,
+    /// Per-host politeness limiter, shared by every scan using these options
+    pub rate_limiter: Arc<HostRateLimiter>,
+    /// Retry policy for transient failures
+    pub retry: RetryPolicy,
+    /// Proxies to rotate through per request; when set, checks never go out directly
+    pub proxies: Option<Arc<ProxyPool>>,
+    /// Selects which sites take part in the scan
+    pub filter: SiteFilter,
+    /// Also probe every site with a random control username to catch sites
+    /// that claim any name exists
+    pub control_check: bool,
+    /// Time allowed for a single request, body included
+    pub timeout: Duration,
+}
 
-pub async fn check_username_from_webserver(
+impl Default for CheckOptions {
+    fn default() -> Self {
+        CheckOptions {
+            concurrency: 10,
+            ordered: false,
+            rate_limiter: Arc::new(HostRateLimiter::default()),
+            retry: RetryPolicy::default(),
+            proxies: None,
+            filter: SiteFilter::default(),
+            control_check: false,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Checks `username` against every site, reporting each result to the console
+pub async fn check_username(
     client: &Client,
     username: &str,
     sites_data: &[SiteData],
-    _threads: usize, // Ignored parameter, but kept for compatibility
-    ws_sink: Option<Arc<Mutex<SplitSink<WebSocketStream, Message>>>>,
+    options: &CheckOptions,
 ) -> Vec<CheckResult> {
-    println!("Checking availability for username: {}\n", username);
+    check_username_with_sink(client, username, sites_data, options, &ConsoleSink).await
+}
 
-    let total = sites_data.len();
-    let mut all_results = Vec::new();
-    let mut completed = 0;
+/// The checking engine: runs every site check concurrently and hands each
+/// result to `sink` as soon as it completes
+pub async fn check_username_with_sink(
+    client: &Client,
+    username: &str,
+    sites_data: &[SiteData],
+    options: &CheckOptions,
+    sink: &dyn ProgressSink,
+) -> Vec<CheckResult> {
+    let sites = options.filter.apply(sites_data);
+    let total = sites.len();
+    sink.on_start(username, total);
 
-    for site in sites_data {
-        let uri_string = site.uri_check.replace("{account}", username);
-        let domain = extract_domain(&uri_string).unwrap_or_else(|| "unknown.com".to_string());
-        let logo_url = get_site_logo(&domain);
+    // Keep exactly `concurrency` checks in flight, a slow site only holds its own slot
+    let concurrency = options.concurrency.max(1);
+    let control = options.control_check.then(random_username);
+    let checks = stream::iter(sites)
+        .map(|site| check_with_control(client, site, username, control.as_deref(), options));
+    let mut checks = if options.ordered {
+        checks.buffered(concurrency).boxed()
+    } else {
+        checks.buffer_unordered(concurrency).boxed()
+    };
 
-        let (is_taken, status, error) = match check_site(client, site, &uri_string).await {
-            Ok((is_taken, status)) => (is_taken, status, None),
-            Err(e) => (false, "Error".to_string(), Some(e.to_string())),
-        };
+    let mut all_results = Vec::with_capacity(total);
+    while let Some(result) = checks.next().await {
+        sink.on_result(&result, all_results.len() + 1, total);
+        all_results.push(result);
+    }
 
-        completed += 1;
-
-        if let Some(ws_sink) = &ws_sink {
-            let update = ProgressUpdate {
-                site: site.name.clone(),
-                status: status.clone(),
-                url: uri_string.clone(),
-                logo_url: logo_url.clone(),
-                error: error.clone(),
-                is_taken,
-                completed,
-                total,
-            };
-            if let Ok(json) = serde_json::to_string(&update) {
-                let mut sink = ws_sink.lock().await;
-                if sink.send(Message::Text(json.into())).await.is_err() {
-                    eprintln!("Failed to send WebSocket message");
-                }
-            }
-        }
+    sink.on_complete(username, &all_results);
+    all_results
+}
 
-        let color = if is_taken { "red" } else { "green" };
-        println!("{} {} - {}", status.color(color), site.name, uri_string);
+/// Checks a single site, and when a control username is given probes it
+/// alongside. A site that claims the random control username exists shows
+/// its "exists" markers for any name, so a Taken verdict is indeterminate.
+/// Both names being available is the expected answer and stays Available.
+async fn check_with_control(
+    client: &Client,
+    site: &SiteData,
+    username: &str,
+    control: Option<&str>,
+    options: &CheckOptions,
+) -> CheckResult {
+    let Some(control) = control else {
+        return check_one(client, site, username, options).await;
+    };
+
+    let (mut result, control_result) = futures_util::future::join(
+        check_one(client, site, username, options),
+        check_one(client, site, control, options),
+    )
+    .await;
+    if result.verdict == Verdict::Taken && control_result.verdict == Verdict::Taken {
+        result.verdict = Verdict::Indeterminate;
+    }
+    result.control_verdict = Some(control_result.verdict);
+    result
+}
+
+/// Checks a single site and wraps the outcome into a `CheckResult`
+async fn check_one(
+    client: &Client,
+    site: &SiteData,
+    username: &str,
+    options: &CheckOptions,
+) -> CheckResult {
+    let account = site.account_for(username);
+    let uri_string = site.uri_check.replace("{account}", &account);
+    let profile_url = site.uri_pretty.as_ref().map_or_else(
+        || uri_string.clone(),
+        |pretty| pretty.replace("{account}", &account),
+    );
+    let domain = extract_domain(&profile_url).unwrap_or_else(|| "unknown.com".to_string());
+    let logo_url = get_site_logo(&domain);
 
-        all_results.push(CheckResult {
+    if let Err(reason) = site.validate_account(&account) {
+        return CheckResult {
             site: site.name.clone(),
-            status,
-            url: uri_string,
+            category: site.cat.clone(),
+            verdict: Verdict::Invalid,
+            check_url: uri_string,
+            profile_url,
             logo_url,
-            error,
-        });
-
-        sleep(Duration::from_millis(100)).await;
+            error: Some(reason),
+            error_kind: None,
+            attempts: 0,
+            control_verdict: None,
+            http_status: None,
+            latency_ms: None,
+        };
     }
 
-    if let Some(ws_sink) = &ws_sink {
-        let completion_msg = serde_json::json!({
-            "completed": true,
-            "total": total
-        })
-        .to_string();
-        let mut sink = ws_sink.lock().await;
-        if sink
-            .send(Message::Text(completion_msg.into()))
-            .await
-            .is_err()
-        {
-            eprintln!("Failed to send completion message");
-        }
-    }
+    let outcome = check_site(client, site, &uri_string, &account, options).await;
+    let (verdict, error, error_kind) = match outcome.verdict {
+        Ok(verdict) => (verdict, None, None),
+        Err(failure) => (Verdict::Error, Some(failure.message), Some(failure.kind)),
+    };
 
-    all_results
+    CheckResult {
+        site: site.name.clone(),
+        category: site.cat.clone(),
+        verdict,
+        check_url: uri_string,
+        profile_url,
+        logo_url,
+        error,
+        error_kind,
+        attempts: outcome.attempts,
+        control_verdict: None,
+        http_status: outcome.http_status,
+        latency_ms: outcome.latency.map(|latency| latency.as_millis() as u64),
+    }
 }
 
-pub async fn check_username(
+/// Builds the request for a single site check, honoring the method, body and
+/// headers declared in the site data
+fn build_request(
     client: &Client,
+    site: &SiteData,
+    uri: &str,
     username: &str,
-    sites_data: &[SiteData],
-    threads: usize,
-) -> Vec<CheckResult> {
-    println!("Checking availability for username: {}\n", username);
-
-    // Create a shared client for concurrent requests
-    let client = client.clone();
-
-    // The maximum number of concurrent requests
-    let concurrent_limit = threads;
-
-    // Process sites in chunks to control concurrency
-    let mut all_results = Vec::new();
-
-    // Process sites in chunks to avoid overwhelming APIs
-    for chunk in sites_data.chunks(concurrent_limit) {
-        let mut tasks = Vec::new();
-
-        // Start tasks for each site in the chunk
-        for site in chunk {
-            let site = site.clone();
-            let client = client.clone();
-            let username = username.to_string();
-
-            // Spawn an async task for each site check
-            let task = tokio::spawn(async move {
-                let uri_string = site.uri_check.replace("{account}", &username);
-                let domain =
-                    extract_domain(&uri_string).unwrap_or_else(|| "unknown.com".to_string());
-                let logo_url = get_site_logo(&domain);
-
-                match check_site(&client, &site, &uri_string).await {
-                    Ok((is_taken, status)) => (
-                        site.name.clone(),
-                        status,
-                        uri_string,
-                        logo_url,
-                        None,
-                        is_taken,
-                    ),
-                    Err(e) => (
-                        site.name.clone(),
-                        "Error".to_string(),
-                        uri_string,
-                        logo_url,
-                        Some(e.to_string()),
-                        false,
-                    ),
-                }
-            });
+) -> Result<RequestBuilder, SiteFailure> {
+    let body = site
+        .post_body
+        .as_ref()
+        .map(|body| body.replace("{account}", username));
 
-            tasks.push(task);
-        }
+    let method = match &site.method {
+        Some(method) => Method::from_bytes(method.to_uppercase().as_bytes())
+            .map_err(|e| SiteFailure::new(CheckError::Request, e))?,
+        None if body.is_some() => Method::POST,
+        None => Method::GET,
+    };
 
-        // Wait for all tasks in this chunk to complete
-        for task in tasks {
-            if let Ok((site_name, status, url, logo_url, error, is_taken)) = task.await {
-                let color = if is_taken { "red" } else { "green" };
-                if let Some(err) = &error {
-                    println!("{} {} - {}", "Error".color("yellow"), site_name, err);
-                } else {
-                    println!("{} {} - {}", status.color(color), site_name, url);
-                }
+    let mut request = client.request(method, uri);
+    for (name, value) in &site.headers {
+        request = request.header(name, value.replace("{account}", username));
+    }
+    if let Some(body) = body {
+        request = request.body(body);
+    }
 
-                all_results.push(CheckResult {
-                    site: site_name,
-                    status,
-                    url,
-                    logo_url,
-                    error,
-                });
-            }
-        }
+    Ok(request)
+}
+
+/// Why `check_site` could not reach a verdict
+#[derive(Debug)]
+struct SiteFailure {
+    kind: CheckError,
+    message: String,
+}
 
-        // Add a small delay between chunks to be nice to APIs
-        sleep(Duration::from_millis(200)).await;
+impl SiteFailure {
+    fn new(kind: CheckError, error: impl fmt::Display) -> Self {
+        SiteFailure {
+            kind,
+            message: error.to_string(),
+        }
     }
+}
 
-    all_results
+/// What `check_site` learned from the last request it sent
+struct SiteOutcome {
+    verdict: Result<Verdict, SiteFailure>,
+    /// Number of requests sent, retries included
+    attempts: u32,
+    http_status: Option<u16>,
+    latency: Option<Duration>,
 }
 
+/// Checks a single site, retrying transient failures according to the retry
+/// policy
 async fn check_site(
     client: &Client,
     site: &SiteData,
     uri: &str,
-) -> Result<(bool, String), Box<dyn Error>> {
-    let response = client
-        .get(uri)
-        .timeout(Duration::from_secs(10))
-        .send()
-        .await?;
-
-    let status = response.status();
-    let body = response.text().await?;
-
-    let is_taken = if status.as_u16() == site.e_code && body.contains(&site.e_string) {
-        true
-    } else if status.as_u16() == site.m_code && body.contains(&site.m_string) {
-        // If we get the "missing" code and string, the user does not exist
-        false
-    } else {
-        // In all other cases, assume the user is available
-        false
+    username: &str,
+    options: &CheckOptions,
+) -> SiteOutcome {
+    let host = rate_limit_host(uri);
+    let mut attempts = 0;
+    let mut http_status = None;
+    let mut latency = None;
+    let outcome = |verdict, attempts, http_status, latency| SiteOutcome {
+        verdict,
+        attempts,
+        http_status,
+        latency,
     };
 
-    let status_text = if is_taken {
-        "Taken".to_string()
-    } else {
-        "Available".to_string()
-    };
+    loop {
+        attempts += 1;
+        let proxy = match options.proxies.as_deref().map(ProxyPool::pick).transpose() {
+            Ok(proxy) => proxy,
+            Err(e) => {
+                let failure = SiteFailure::new(CheckError::Proxy, e);
+                return outcome(Err(failure), attempts, http_status, latency);
+            }
+        };
+        let client = proxy.map_or(client, |handle| handle.client);
+        let request = match build_request(client, site, uri, username) {
+            Ok(request) => request,
+            Err(e) => return outcome(Err(e), attempts, http_status, latency),
+        };
 
-    // Here you put the code for real-time status update if needed
+        options.rate_limiter.acquire(&host).await;
+        let started = Instant::now();
+        let response = request.timeout(options.timeout).send().await;
+        if let (Some(pool), Some(handle)) = (&options.proxies, proxy) {
+            match &response {
+                // Slow or broken sites are not the proxy's fault
+                Err(e) if CheckError::classify(e, true) == CheckError::Proxy => {
+                    pool.report_failure(handle)
+                }
+                _ => pool.report_success(handle),
+            }
+        }
 
-    Ok((is_taken, status_text))
-}
+        let error = match response {
+            Ok(response) => {
+                let status = response.status().as_u16();
+                http_status = Some(status);
+                let retry_after = response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| retry::parse_retry_after(value, chrono::Utc::now()));
 
-pub fn save_txt_report(username: &str, results: &[CheckResult]) -> Result<(), Box<dyn Error>> {
-    let filename = format!("{}_report.txt", username);
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(filename)?;
+                if status == 429 {
+                    match retry_after {
+                        Some(delay) => options.rate_limiter.cool_down_for(&host, delay),
+                        None => options.rate_limiter.cool_down(&host),
+                    }
+                }
+                if retry::is_transient_status(status)
+                    && let Some(delay) = options.retry.next_delay(attempts, retry_after)
+                {
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
 
-    writeln!(file, "Username availability report for: {}", username)?;
-    writeln!(file, "Generated on: {}", chrono::Local::now())?;
-    writeln!(file, "{}", "-".repeat(80))?;
+                let headers = response.headers().clone();
+                let body = response.text().await;
+                latency = Some(started.elapsed());
+                match body {
+                    Ok(body) => {
+                        let verdict = evaluate_response(site, status, &headers, &body);
+                        return outcome(Ok(verdict), attempts, http_status, latency);
+                    }
+                    Err(e) => e,
+                }
+            }
+            Err(e) => e,
+        };
 
-    for result in results {
-        writeln!(file, "{}: {}", result.site, result.status)?;
-        writeln!(file, "URL: {}", result.url)?;
-        writeln!(file, "Logo: {}", result.logo_url)?;
-        if let Some(error) = &result.error {
-            writeln!(file, "Error: {}", error)?;
+        if retry::is_transient_error(&error, proxy.is_some())
+            && let Some(delay) = options.retry.next_delay(attempts, None)
+        {
+            tokio::time::sleep(delay).await;
+            continue;
         }
-        writeln!(file, "{}", "-".repeat(40))?;
+        let failure = SiteFailure::new(CheckError::classify(&error, proxy.is_some()), &error);
+        return outcome(Err(failure), attempts, http_status, latency);
     }
+}
 
-    Ok(())
+/// Matches a response against the site's detection rules. Only a positive
+/// match of the "missing" rule counts as available, challenge pages are
+/// blocked and anything else unexpected is inconclusive.
+fn evaluate_response(site: &SiteData, status: u16, headers: &HeaderMap, body: &str) -> Verdict {
+    if status == site.e_code && body.contains(&site.e_string) {
+        Verdict::Taken
+    } else if protection::detect_challenge(status, headers, body).is_some() {
+        Verdict::Blocked
+    } else if status == site.m_code && body.contains(&site.m_string) {
+        Verdict::Available
+    } else {
+        Verdict::Inconclusive
+    }
 }
 
-pub fn save_json_report(username: &str, results: &[CheckResult]) -> Result<(), Box<dyn Error>> {
-    let filename = format!("{}_report.json", username);
-    let report = Report {
-        username: username.to_string(),
-        generated_at: chrono::Local::now().to_string(),
-        results: results.to_vec(),
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{always_found, profile, serve, test_site};
+    use poem::http::{HeaderMap, StatusCode};
+    use poem::{IntoResponse, Route, get, handler, post};
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Records every update so tests can assert on emission order
+    #[derive(Default)]
+    struct CollectingSink {
+        updates: Mutex<Vec<(String, usize, usize)>>,
+        completed: AtomicBool,
+    }
+
+    impl CollectingSink {
+        fn sites(&self) -> Vec<String> {
+            let updates = self.updates.lock().unwrap();
+            updates.iter().map(|(site, _, _)| site.clone()).collect()
+        }
+    }
+
+    impl ProgressSink for CollectingSink {
+        fn on_result(&self, result: &CheckResult, completed: usize, total: usize) {
+            let mut updates = self.updates.lock().unwrap();
+            updates.push((result.site.clone(), completed, total));
+        }
+
+        fn on_complete(&self, _username: &str, _results: &[CheckResult]) {
+            self.completed.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[handler]
+    fn graphql(headers: &HeaderMap, body: String) -> impl IntoResponse {
+        let is_json = headers
+            .get("content-type")
+            .is_some_and(|value| value == "application/json");
+        if is_json && body.contains("\\\"johndoe\\\"") {
+            r#"{"data":{"User":{"id":1}}}"#.with_status(StatusCode::OK)
+        } else {
+            "Not Found".with_status(StatusCode::NOT_FOUND)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_site_sends_post_body_and_headers() {
+        let base = serve(Route::new().at("/graphql", post(graphql))).await;
+        let mut site = test_site(format!("{}/graphql", base));
+        site.uri_pretty = Some("https://anilist.co/user/{account}".to_string());
+        site.post_body = Some(r#"{"query":"query{User(name:\"{account}\"){id}}"}"#.to_string());
+        site.headers
+            .insert("Content-Type".to_string(), "application/json".to_string());
+
+        let client = Client::new();
+        let options = CheckOptions::default();
+        let verdict = check_site(&client, &site, &site.uri_check, "johndoe", &options)
+            .await
+            .verdict
+            .unwrap();
+        assert_eq!(
+            verdict,
+            Verdict::Taken,
+            "POST body should carry the substituted username"
+        );
+
+        let result = check_one(&client, &site, "johndoe", &options).await;
+        assert_eq!(result.check_url, format!("{}/graphql", base));
+        assert_eq!(result.profile_url, "https://anilist.co/user/johndoe");
+
+        site.headers.clear();
+        let verdict = check_site(&client, &site, &site.uri_check, "johndoe", &options)
+            .await
+            .verdict
+            .unwrap();
+        assert_eq!(
+            verdict,
+            Verdict::Available,
+            "request without the declared headers should miss"
+        );
+    }
 
-    let file = File::create(filename)?;
-    serde_json::to_writer_pretty(file, &report)?;
+    #[tokio::test]
+    async fn test_check_site_falls_back_to_get() {
+        let base = serve(Route::new().at("/:name", get(profile))).await;
+        let site = test_site(format!("{}/{{account}}", base));
+        let uri = site.uri_check.replace("{account}", "johndoe");
 
-    Ok(())
+        let verdict = check_site(
+            &Client::new(),
+            &site,
+            &uri,
+            "johndoe",
+            &CheckOptions::default(),
+        )
+        .await
+        .verdict
+        .unwrap();
+        assert_eq!(verdict, Verdict::Taken);
+    }
+
+    #[handler]
+    async fn slow_profile() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        r#"{"id": 1}"#
+    }
+
+    #[tokio::test]
+    async fn test_check_username_ordering() {
+        let base = serve(
+            Route::new()
+                .at("/slow/:name", get(slow_profile))
+                .at("/fast/:name", get(profile)),
+        )
+        .await;
+        let mut slow = test_site(format!("{}/slow/{{account}}", base));
+        slow.name = "Slow".to_string();
+        let mut fast = test_site(format!("{}/fast/{{account}}", base));
+        fast.name = "Fast".to_string();
+        let sites = vec![slow, fast];
+        let client = Client::new();
+
+        let mut options = CheckOptions {
+            concurrency: 2,
+            ..CheckOptions::default()
+        };
+        let collector = CollectingSink::default();
+        check_username_with_sink(&client, "johndoe", &sites, &options, &collector).await;
+        let names: Vec<_> = collector.sites();
+        assert_eq!(names, ["Fast", "Slow"], "results arrive as they complete");
+
+        options.ordered = true;
+        let collector = CollectingSink::default();
+        check_username_with_sink(&client, "johndoe", &sites, &options, &collector).await;
+        assert_eq!(
+            collector.sites(),
+            ["Slow", "Fast"],
+            "ordered keeps data file order"
+        );
+        assert!(collector.completed.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_check_site_retries_transient_status() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Route::new().at(
+            "/:name",
+            get(poem::endpoint::make(move |_| {
+                let counter = counter.clone();
+                async move {
+                    if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                        "Service Unavailable"
+                            .with_status(StatusCode::SERVICE_UNAVAILABLE)
+                            .with_header("Retry-After", "0")
+                            .into_response()
+                    } else {
+                        r#"{"id": 1}"#.into_response()
+                    }
+                }
+            })),
+        );
+        let base = serve(app).await;
+        let site = test_site(format!("{}/{{account}}", base));
+        let uri = site.uri_check.replace("{account}", "johndoe");
+
+        let SiteOutcome {
+            verdict, attempts, ..
+        } = check_site(
+            &Client::new(),
+            &site,
+            &uri,
+            "johndoe",
+            &CheckOptions::default(),
+        )
+        .await;
+        assert_eq!(verdict.unwrap(), Verdict::Taken);
+        assert_eq!(attempts, 2);
+
+        let options = CheckOptions {
+            retry: RetryPolicy::none(),
+            ..CheckOptions::default()
+        };
+        hits.store(0, Ordering::SeqCst);
+        let SiteOutcome {
+            verdict, attempts, ..
+        } = check_site(&Client::new(), &site, &uri, "johndoe", &options).await;
+        assert_eq!(verdict.unwrap(), Verdict::Inconclusive);
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_check_site_goes_through_proxy() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        // Stand-in for an HTTP proxy: answers absolute-form requests itself
+        let proxy = serve(Route::new().at(
+            "/:name",
+            get(poem::endpoint::make(move |_| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    r#"{"id": 1}"#
+                }
+            })),
+        ))
+        .await;
+        let dead_proxy = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+
+        let site = test_site("http://firststep.invalid/{account}".to_string());
+        let uri = site.uri_check.replace("{account}", "johndoe");
+        let pool = ProxyPool::new(&[dead_proxy, proxy.clone()], Rotation::RoundRobin)
+            .unwrap()
+            .with_max_failures(1);
+        let options = CheckOptions {
+            proxies: Some(Arc::new(pool)),
+            retry: RetryPolicy {
+                base_delay: Duration::from_millis(10),
+                ..RetryPolicy::default()
+            },
+            ..CheckOptions::default()
+        };
+
+        let SiteOutcome {
+            verdict, attempts, ..
+        } = check_site(&Client::new(), &site, &uri, "johndoe", &options).await;
+        assert_eq!(verdict.unwrap(), Verdict::Taken);
+        assert_eq!(
+            attempts, 2,
+            "the dead proxy fails first, then rotation moves on"
+        );
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert_eq!(options.proxies.unwrap().healthy(), [proxy.as_str()]);
+    }
+
+    #[tokio::test]
+    async fn test_check_one_strips_and_validates() {
+        let base = serve(Route::new().at("/:name", get(profile))).await;
+        let mut site = test_site(format!("{}/{{account}}", base));
+        site.strip_bad_char = Some(".".to_string());
+
+        assert_eq!(site.account_for("john.doe"), "johndoe");
+        let options = CheckOptions::default();
+        let result = check_one(&Client::new(), &site, "john.doe", &options).await;
+        assert_eq!(result.check_url, format!("{}/johndoe", base));
+        assert_eq!(result.profile_url, result.check_url);
+        assert_eq!(result.verdict, Verdict::Taken);
+
+        site.username_max_length = Some(5);
+        let result = check_one(&Client::new(), &site, "john.doe", &options).await;
+        assert_eq!(result.verdict, Verdict::Invalid);
+        assert_eq!(result.attempts, 0, "invalid names are never probed");
+
+        site.username_max_length = None;
+        site = site.with_username_pattern(UsernamePattern::new("[a-z0-9_]+").unwrap());
+        assert!(site.validate_account("john_doe").is_ok());
+        assert!(site.validate_account("john-doe").is_err());
+        assert!(site.validate_account("").is_err());
+
+        let mut json = serde_json::to_value(&site).unwrap();
+        assert_eq!(json["username_pattern"], "[a-z0-9_]+");
+        json["username_pattern"] = "[a-z".into();
+        let error = serde_json::from_value::<SiteData>(json).unwrap_err();
+        assert!(
+            error.to_string().contains("regex parse error"),
+            "bad patterns fail the load: {}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_one_keeps_error_kind() {
+        let site = test_site("http://127.0.0.1:1/{account}".to_string());
+        let options = CheckOptions {
+            retry: RetryPolicy::none(),
+            ..CheckOptions::default()
+        };
+        let result = check_one(&Client::new(), &site, "johndoe", &options).await;
+        assert_eq!(result.verdict, Verdict::Error);
+        assert_eq!(result.error_kind, Some(CheckError::Connect));
+        assert!(result.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_control_username_flags_indeterminate() {
+        let base = serve(
+            Route::new()
+                .at("/any/:name", get(always_found))
+                .at("/real/:name", get(profile)),
+        )
+        .await;
+        let mut any = test_site(format!("{}/any/{{account}}", base));
+        any.name = "Any".to_string();
+        let mut real = test_site(format!("{}/real/{{account}}", base));
+        real.name = "Real".to_string();
+        let sites = vec![any, real];
+        let options = CheckOptions {
+            control_check: true,
+            ordered: true,
+            ..CheckOptions::default()
+        };
+
+        let collector = CollectingSink::default();
+        let results =
+            check_username_with_sink(&Client::new(), "johndoe", &sites, &options, &collector).await;
+        assert_eq!(results[0].verdict, Verdict::Indeterminate);
+        assert_eq!(results[0].control_verdict, Some(Verdict::Taken));
+        assert_eq!(results[1].verdict, Verdict::Taken);
+        assert_eq!(results[1].control_verdict, Some(Verdict::Available));
+
+        let results =
+            check_username_with_sink(&Client::new(), "janedoe", &sites, &options, &collector).await;
+        assert_eq!(results[1].verdict, Verdict::Available);
+        assert_eq!(results[1].control_verdict, Some(Verdict::Available));
+    }
+
+    #[test]
+    fn test_rate_limit_host() {
+        assert_eq!(
+            rate_limit_host("https://www.skoob.com.br/usuario/johndoe"),
+            "www.skoob.com.br"
+        );
+        assert_ne!(
+            rate_limit_host("https://a.com.br/johndoe"),
+            rate_limit_host("https://b.com.br/johndoe")
+        );
+        assert_eq!(rate_limit_host("not a url"), "");
+    }
+
+    #[test]
+    fn test_evaluate_response() {
+        let site = test_site("https://example.com/{account}".to_string());
+        let headers = HeaderMap::new();
+
+        assert_eq!(
+            evaluate_response(&site, 200, &headers, r#"{"id": 7}"#),
+            Verdict::Taken
+        );
+        assert_eq!(
+            evaluate_response(&site, 404, &headers, "Not Found"),
+            Verdict::Available
+        );
+        assert_eq!(
+            evaluate_response(&site, 429, &headers, "Too Many Requests"),
+            Verdict::Inconclusive
+        );
+        assert_eq!(
+            evaluate_response(&site, 200, &headers, "<title>Just a moment...</title>"),
+            Verdict::Blocked
+        );
+        assert_eq!(
+            evaluate_response(
+                &site,
+                404,
+                &headers,
+                "Not Found <script>window._cf_chl_opt={}</script>"
+            ),
+            Verdict::Blocked,
+            "a challenge page is never reported as available"
+        );
+    }
 }
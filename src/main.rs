@@ -1,73 +1,425 @@
 mod cliargs;
-use cliargs::CliArgs;
+use cliargs::{CliArgs, Command, SitesCommand};
 use reqwest::Client;
 use std::error::Error;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Write, stdin};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 mod server;
 mod templates;
 
+use firststep_name_lib::batch::{
+    check_usernames, read_usernames, save_batch_report, unsupported_batch_format, write_ranking,
+};
+use firststep_name_lib::diff::{diff_reports, write_diff_markdown, write_diff_text};
+use firststep_name_lib::selftest::selftest_sites;
+use firststep_name_lib::variants::{VariantOptions, generate_variants};
+use firststep_name_lib::watch::{Notifier, Watcher};
 use firststep_name_lib::{
-    SitesFile, check_username, download_sites_data, save_json_report, save_txt_report,
+    ConsoleSink, HistoryStore, NdjsonSink, ProgressSink, Report, ReportFormat, SitesFile,
+    StderrSink, build_client, check_username, check_username_with_sink, download_sites_data,
+    save_report,
 };
-use server::run_server;
+use server::{ServerConfig, run_server};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let matches = CliArgs::parse();
 
-    if matches.output == "web" {
-        // Run in web server mode
-        if let Err(e) = run_server().await {
-            eprintln!("Failed to start web server: {}", e);
-            return Err(e.into());
+    match &matches.command {
+        Some(Command::Sites {
+            command: SitesCommand::Selftest { report, known },
+        }) => run_selftest(&matches, report.as_ref(), *known).await?,
+        Some(Command::Diff {
+            before,
+            after,
+            format,
+        }) => run_diff(before, after, format)?,
+        Some(Command::History {
+            username,
+            changes,
+            format,
+        }) => run_history(&matches, username, *changes, format)?,
+        Some(Command::Watch {
+            usernames,
+            every,
+            webhook,
+            exec,
+        }) => {
+            let mut notifiers = vec![Notifier::Stdout];
+            notifiers.extend(webhook.iter().cloned().map(Notifier::Webhook));
+            notifiers.extend(exec.iter().cloned().map(Notifier::Command));
+            run_watch(&matches, usernames, *every, notifiers).await?
         }
-    } else {
-        // Create an HTTP client with reasonable defaults
-        let client = Client::builder()
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
-            .timeout(Duration::from_secs(30))
-            .build()?;
-
-        let json_file = matches.file;
-        let username = matches.username;
-        let output_format = matches.output;
-
-        // Handle download option
-        if matches.download {
-            download_sites_data(&client, json_file.display().to_string().as_str()).await?;
+        Some(Command::Variants {
+            base,
+            limit,
+            list,
+            no_separators,
+            no_suffixes,
+            no_prefixes,
+            no_leet,
+            no_truncation,
+        }) => {
+            let options = VariantOptions {
+                separators: !no_separators,
+                suffixes: !no_suffixes,
+                prefixes: !no_prefixes,
+                leet: !no_leet,
+                truncation: !no_truncation,
+                limit: *limit,
+            };
+            run_variants(&matches, base, &options, *list).await?
+        }
+        None if matches.output == "web" => {
+            // Run in web server mode
+            let config = ServerConfig {
+                options: matches.check_options()?,
+                proxies: matches.proxies()?,
+                sites_data: None,
+                history: matches.history_path(),
+                webhooks: matches.completion_webhooks(),
+            };
+            if let Err(e) = run_server(config).await {
+                eprintln!("Failed to start web server: {}", e);
+                return Err(e.into());
+            }
         }
+        None if matches.usernames_file.is_some() => run_batch(&matches).await?,
+        None => run_scan(&matches).await?,
+    }
+
+    Ok(())
+}
+
+/// Creates an HTTP client with reasonable defaults, through the first proxy if any
+fn client_for(matches: &CliArgs) -> Result<Client, Box<dyn Error>> {
+    let proxies = matches.proxies()?;
+    Ok(build_client(proxies.first().map(String::as_str))?)
+}
+
+/// Loads the site data file, downloading it first when asked to or when missing
+async fn load_sites_data(client: &Client, matches: &CliArgs) -> Result<SitesFile, Box<dyn Error>> {
+    let json_file = &matches.file;
+
+    // Handle download option
+    if matches.download {
+        download_sites_data(client, json_file.display().to_string().as_str()).await?;
+    }
+
+    // Check if the data file exists
+    if !json_file.as_path().exists() {
+        eprintln!(
+            "Data file {} not found. Downloading from GitHub...",
+            json_file.display()
+        );
+        download_sites_data(client, json_file.display().to_string().as_str()).await?;
+    }
+
+    let sites_data = SitesFile::load(json_file)?;
+
+    let filter = matches.site_filter();
+    for category in filter.unknown_categories(sites_data.categories()) {
+        eprintln!("Warning: unknown category {:?}", category);
+    }
+    Ok(sites_data)
+}
+
+async fn run_scan(matches: &CliArgs) -> Result<(), Box<dyn Error>> {
+    let Some(username) = &matches.username else {
+        return Err("--username is required".into());
+    };
+
+    let client = client_for(matches)?;
+    let sites_data = load_sites_data(&client, matches).await?;
+    let options = matches.check_options()?;
 
-        // Check if the data file exists
-        if !json_file.as_path().exists() {
-            println!(
-                "Data file {} not found. Downloading from GitHub...",
-                json_file.display().to_string()
-            );
-            download_sites_data(&client, json_file.display().to_string().as_str()).await?;
+    // Stream results to stdout instead of writing a report file
+    if matches.output == "ndjson" {
+        let results = check_username_with_sink(
+            &client,
+            username.as_str(),
+            &sites_data.sites,
+            &options,
+            &NdjsonSink::default(),
+        )
+        .await;
+        finish_scans(matches, &[Report::new(username, results)]).await;
+        return Ok(());
+    }
+
+    let format: ReportFormat = matches.output.parse()?;
+    let destination = matches.report_destination();
+
+    // Check username availability, keeping stdout for the report if it goes there
+    let results = if destination.is_stdout() {
+        check_username_with_sink(
+            &client,
+            username.as_str(),
+            &sites_data.sites,
+            &options,
+            &StderrSink,
+        )
+        .await
+    } else {
+        check_username(&client, username.as_str(), &sites_data.sites, &options).await
+    };
+
+    // Save the report
+    if let Some(path) = save_report(&destination, format, username.as_str(), &results)? {
+        println!("\nReport saved to {}", path.display());
+    }
+    finish_scans(matches, &[Report::new(username, results)]).await;
+    Ok(())
+}
+
+async fn run_batch(matches: &CliArgs) -> Result<(), Box<dyn Error>> {
+    let Some(path) = &matches.usernames_file else {
+        return Err("--usernames-file is required".into());
+    };
+    let usernames = if path.as_os_str() == "-" {
+        read_usernames(stdin().lock())?
+    } else {
+        read_usernames(BufReader::new(File::open(path)?))?
+    };
+    if usernames.is_empty() {
+        return Err(format!("No usernames found in {}", path.display()).into());
+    }
+    check_and_rank(matches, &usernames, "batch").await
+}
+
+async fn run_variants(
+    matches: &CliArgs,
+    base: &str,
+    options: &VariantOptions,
+    list: bool,
+) -> Result<(), Box<dyn Error>> {
+    let variants = generate_variants(base, options);
+    if variants.is_empty() {
+        return Err(format!("Cannot derive variants from {:?}", base).into());
+    }
+    if list {
+        for variant in &variants {
+            println!("{}", variant);
         }
+        return Ok(());
+    }
+    check_and_rank(matches, &variants, &format!("{}_variants", variants[0])).await
+}
 
-        // Read and parse the JSON file
-        let file = File::open(json_file)?;
-        let reader = BufReader::new(file);
-        let sites_data: SitesFile = serde_json::from_reader(reader)?;
+/// Checks several usernames, prints the ranking and saves the combined report
+async fn check_and_rank(
+    matches: &CliArgs,
+    usernames: &[String],
+    report_name: &str,
+) -> Result<(), Box<dyn Error>> {
+    let streaming = matches.output == "ndjson";
+    let format = if streaming {
+        None
+    } else {
+        Some(matches.output.parse::<ReportFormat>()?)
+    };
+    // Fail before the scan rather than after checking every candidate
+    if let Some(format) = format
+        && !format.supports_batch()
+    {
+        return Err(unsupported_batch_format(format).into());
+    }
+    let destination = matches.report_destination();
 
-        let threads = matches.threads;
+    let client = client_for(matches)?;
+    let sites_data = load_sites_data(&client, matches).await?;
+    let options = matches.check_options()?;
 
-        // Check username availability
-        let results = check_username(&client, username.as_str(), &sites_data.sites, threads).await;
+    // Keep stdout machine-readable when it carries results or the report
+    let quiet = streaming || destination.is_stdout();
+    let sink: Box<dyn ProgressSink> = if streaming {
+        Box::new(NdjsonSink::default())
+    } else if quiet {
+        Box::new(StderrSink)
+    } else {
+        Box::new(ConsoleSink)
+    };
+    let report = check_usernames(&client, usernames, &sites_data.sites, &options, &*sink).await;
+    finish_scans(matches, &report.reports).await;
 
-        // Save the report
-        match output_format.as_str() {
-            "txt" => save_txt_report(username.as_str(), &results)?,
-            "json" => save_json_report(username.as_str(), &results)?,
-            _ => println!("Unsupported output format: {}", output_format),
+    if quiet {
+        eprintln!();
+        write_ranking(&mut std::io::stderr(), &report.summary)?;
+    } else {
+        println!();
+        write_ranking(&mut std::io::stdout(), &report.summary)?;
+    }
+
+    if let Some(format) = format
+        && let Some(path) = save_batch_report(&destination, format, report_name, &report)?
+    {
+        println!("\nReport saved to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Records finished scans and announces them to the completion webhooks
+async fn finish_scans(matches: &CliArgs, reports: &[Report]) {
+    record_history(matches, reports);
+    let webhooks = matches.completion_webhooks();
+    for report in reports {
+        webhooks.deliver(report).await;
+    }
+}
+
+/// Records finished scans in the history database; failures only warn so
+/// that the report still gets written
+fn record_history(matches: &CliArgs, reports: &[Report]) {
+    let Some(path) = matches.history_path() else {
+        return;
+    };
+    let recorded = HistoryStore::open(&path).and_then(|store| {
+        let now = chrono::Utc::now();
+        reports
+            .iter()
+            .try_for_each(|report| store.record(&report.username, &report.results, now))
+    });
+    if let Err(e) = recorded {
+        eprintln!(
+            "Warning: failed to record history in {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+async fn run_watch(
+    matches: &CliArgs,
+    usernames: &[String],
+    every: Duration,
+    notifiers: Vec<Notifier>,
+) -> Result<(), Box<dyn Error>> {
+    let client = client_for(matches)?;
+    let sites_data = load_sites_data(&client, matches).await?;
+    let options = matches.check_options()?;
+
+    eprintln!(
+        "Watching {} every {}s, press Ctrl+C to stop",
+        usernames.join(", "),
+        every.as_secs()
+    );
+    let mut watcher = Watcher::new(usernames.to_vec(), notifiers);
+    let on_round = |reports: &[Report]| {
+        record_history(matches, reports);
+        eprintln!(
+            "Round finished at {}",
+            chrono::Local::now().format("%H:%M:%S")
+        );
+    };
+    tokio::select! {
+        _ = watcher.run(&client, &sites_data.sites, &options, every, on_round) => {}
+        _ = tokio::signal::ctrl_c() => eprintln!("Stopping watch"),
+    }
+    Ok(())
+}
+
+fn run_history(
+    matches: &CliArgs,
+    username: &str,
+    changes: bool,
+    format: &str,
+) -> Result<(), Box<dyn Error>> {
+    let Some(path) = &matches.history_db else {
+        return Err("No history database given, pass --history-db".into());
+    };
+    if !path.exists() {
+        return Err(format!("No history database at {}", path.display()).into());
+    }
+    let store = HistoryStore::open(path)?;
+    let mut timelines = store.timeline(username, &matches.sites)?;
+    if changes {
+        for timeline in &mut timelines {
+            timeline.entries = timeline.changes().into_iter().cloned().collect();
         }
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    if format == "json" {
+        serde_json::to_writer_pretty(&mut stdout, &timelines)?;
+        writeln!(stdout)?;
+        return Ok(());
+    }
+    if timelines.is_empty() {
+        writeln!(stdout, "No history for username: {}", username)?;
+    }
+    for timeline in &timelines {
+        writeln!(stdout, "{}", timeline.site)?;
+        for entry in &timeline.entries {
+            let status = entry.http_status.map(|s| s.to_string()).unwrap_or_default();
+            let latency = entry
+                .latency_ms
+                .map(|ms| format!("{} ms", ms))
+                .unwrap_or_default();
+            writeln!(
+                stdout,
+                "  {}  {:<22} {:>4} {:>8}",
+                entry.checked_at, entry.verdict, status, latency
+            )?;
+        }
+    }
+    Ok(())
+}
 
-        println!("\nReport saved to {}_report.{}", username, output_format);
+fn run_diff(before: &Path, after: &Path, format: &str) -> Result<(), Box<dyn Error>> {
+    let before = Report::load(before)?;
+    let after = Report::load(after)?;
+    if before.username != after.username {
+        eprintln!(
+            "Warning: comparing reports of different usernames ({} and {})",
+            before.username, after.username
+        );
     }
 
+    let diff = diff_reports(&before, &after);
+    let mut stdout = std::io::stdout().lock();
+    match format {
+        "json" => {
+            serde_json::to_writer_pretty(&mut stdout, &diff)?;
+            writeln!(stdout)?;
+        }
+        "markdown" => write_diff_markdown(&mut stdout, &diff)?,
+        _ => write_diff_text(&mut stdout, &diff)?,
+    }
+    Ok(())
+}
+
+async fn run_selftest(
+    matches: &CliArgs,
+    report_file: Option<&PathBuf>,
+    known: usize,
+) -> Result<(), Box<dyn Error>> {
+    let client = client_for(matches)?;
+    let sites_data = load_sites_data(&client, matches).await?;
+    let options = matches.check_options()?;
+
+    eprintln!(
+        "Self-testing {} site definitions...",
+        sites_data.sites.len()
+    );
+    let report = selftest_sites(&client, &sites_data.sites, &options, known).await;
+
+    for site in report.broken_sites() {
+        eprintln!("Broken: {}", site);
+    }
+    eprintln!(
+        "Healthy: {}, Broken: {}, Unverified: {}",
+        report.healthy, report.broken, report.unverified
+    );
+
+    match report_file {
+        Some(path) => {
+            serde_json::to_writer_pretty(File::create(path)?, &report)?;
+            eprintln!("Health report saved to {}", path.display());
+        }
+        None => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
     Ok(())
 }
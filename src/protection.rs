@@ -0,0 +1,59 @@
+use reqwest::header::HeaderMap;
+
+/// Body markers of challenge pages, paired with the protection serving them
+const CHALLENGE_MARKERS: &[(&str, &str)] = &[
+    ("<title>Just a moment...</title>", "cloudflare"),
+    ("Attention Required! | Cloudflare", "cloudflare"),
+    (
+        "challenges.cloudflare.com/cdn-cgi/challenge-platform",
+        "cloudflare",
+    ),
+    ("window._cf_chl_opt", "cloudflare"),
+    ("hcaptcha.com/1/api.js", "hcaptcha"),
+    ("class=\"h-captcha\"", "hcaptcha"),
+    ("captcha-delivery.com", "datadome"),
+];
+
+/// Looks for a bot-protection challenge in a response and names the vendor
+pub fn detect_challenge(status: u16, headers: &HeaderMap, body: &str) -> Option<&'static str> {
+    if headers
+        .get("cf-mitigated")
+        .is_some_and(|value| value == "challenge")
+    {
+        return Some("cloudflare");
+    }
+    if status == 403 && headers.contains_key("x-datadome") {
+        return Some("datadome");
+    }
+
+    CHALLENGE_MARKERS
+        .iter()
+        .find(|(marker, _)| body.contains(marker))
+        .map(|(_, vendor)| *vendor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_challenge() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(detect_challenge(200, &headers, "<h1>johndoe</h1>"), None);
+        assert_eq!(
+            detect_challenge(403, &headers, "<html><title>Just a moment...</title>"),
+            Some("cloudflare")
+        );
+        assert_eq!(
+            detect_challenge(200, &headers, r#"<div class="h-captcha" data-sitekey="x">"#),
+            Some("hcaptcha")
+        );
+
+        headers.insert("x-datadome", "protected".parse().unwrap());
+        assert_eq!(detect_challenge(200, &headers, ""), None);
+        assert_eq!(detect_challenge(403, &headers, ""), Some("datadome"));
+
+        headers.insert("cf-mitigated", "challenge".parse().unwrap());
+        assert_eq!(detect_challenge(200, &headers, ""), Some("cloudflare"));
+    }
+}
@@ -0,0 +1,251 @@
+use rand::random_range;
+use reqwest::Client;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Consecutive connection failures after which a proxy is taken out of rotation
+const DEFAULT_MAX_FAILURES: u32 = 3;
+/// Time an unhealthy proxy sits out before it gets another chance
+const DEFAULT_RECOVERY: Duration = Duration::from_secs(300);
+
+/// How the next proxy is chosen for each request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    RoundRobin,
+    Random,
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(Rotation::RoundRobin),
+            "random" => Ok(Rotation::Random),
+            _ => Err(format!("Unknown proxy rotation: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ProxyEntry {
+    url: String,
+    client: Client,
+    failures: AtomicU32,
+    /// When the proxy last failed while already unhealthy or turning so
+    benched_at: Mutex<Option<Instant>>,
+}
+
+/// A client routed through one proxy of the pool
+#[derive(Debug, Clone, Copy)]
+pub struct ProxyHandle<'a> {
+    index: usize,
+    pub client: &'a Client,
+}
+
+/// Error returned once every proxy of the pool has been marked unhealthy
+#[derive(Debug)]
+pub struct NoHealthyProxy;
+
+impl fmt::Display for NoHealthyProxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("No healthy proxy left in the pool")
+    }
+}
+
+impl Error for NoHealthyProxy {}
+
+/// Set of proxies used in rotation, one `reqwest::Client` per proxy
+#[derive(Debug)]
+pub struct ProxyPool {
+    entries: Vec<ProxyEntry>,
+    rotation: Rotation,
+    next: AtomicUsize,
+    max_failures: u32,
+    recovery: Duration,
+}
+
+impl ProxyPool {
+    /// Builds a pool from proxy URLs (`http://`, `https://` or `socks5://`)
+    pub fn new(urls: &[String], rotation: Rotation) -> Result<Self, reqwest::Error> {
+        let entries = urls
+            .iter()
+            .map(|url| {
+                Ok(ProxyEntry {
+                    url: url.clone(),
+                    client: crate::build_client(Some(url))?,
+                    failures: AtomicU32::new(0),
+                    benched_at: Mutex::new(None),
+                })
+            })
+            .collect::<Result<Vec<_>, reqwest::Error>>()?;
+
+        Ok(ProxyPool {
+            entries,
+            rotation,
+            next: AtomicUsize::new(0),
+            max_failures: DEFAULT_MAX_FAILURES,
+            recovery: DEFAULT_RECOVERY,
+        })
+    }
+
+    /// Sets how many consecutive failures mark a proxy unhealthy
+    pub fn with_max_failures(mut self, max_failures: u32) -> Self {
+        self.max_failures = max_failures.max(1);
+        self
+    }
+
+    /// Sets how long an unhealthy proxy sits out before it is tried again
+    pub fn with_recovery(mut self, recovery: Duration) -> Self {
+        self.recovery = recovery;
+        self
+    }
+
+    /// Healthy proxies are below the failure limit; unhealthy ones get one
+    /// more try once the recovery time has passed
+    fn is_healthy(&self, entry: &ProxyEntry) -> bool {
+        if entry.failures.load(Ordering::Relaxed) < self.max_failures {
+            return true;
+        }
+        let benched_at = *entry.benched_at.lock().unwrap();
+        benched_at.is_some_and(|at| at.elapsed() >= self.recovery)
+    }
+
+    /// Picks the proxy for the next request, skipping unhealthy ones
+    pub fn pick(&self) -> Result<ProxyHandle<'_>, NoHealthyProxy> {
+        let healthy: Vec<usize> = (0..self.entries.len())
+            .filter(|&index| self.is_healthy(&self.entries[index]))
+            .collect();
+        if healthy.is_empty() {
+            return Err(NoHealthyProxy);
+        }
+
+        let index = match self.rotation {
+            Rotation::RoundRobin => {
+                healthy[self.next.fetch_add(1, Ordering::Relaxed) % healthy.len()]
+            }
+            Rotation::Random => healthy[random_range(0..healthy.len())],
+        };
+        Ok(ProxyHandle {
+            index,
+            client: &self.entries[index].client,
+        })
+    }
+
+    /// Resets the failure streak of the proxy behind `handle`
+    pub fn report_success(&self, handle: ProxyHandle<'_>) {
+        let entry = &self.entries[handle.index];
+        entry.failures.store(0, Ordering::Relaxed);
+        *entry.benched_at.lock().unwrap() = None;
+    }
+
+    /// Counts a failure to reach the proxy behind `handle` against it
+    pub fn report_failure(&self, handle: ProxyHandle<'_>) {
+        let entry = &self.entries[handle.index];
+        let failures = entry.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.max_failures {
+            *entry.benched_at.lock().unwrap() = Some(Instant::now());
+        }
+        if failures == self.max_failures {
+            eprintln!(
+                "Proxy {} marked unhealthy after {} failures",
+                entry.url, failures
+            );
+        }
+    }
+
+    /// URLs of the proxies still in rotation
+    pub fn healthy(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|entry| self.is_healthy(entry))
+            .map(|entry| entry.url.as_str())
+            .collect()
+    }
+}
+
+/// Reads proxy URLs from a file, one per line; blank lines and `#` comments are skipped
+pub fn read_proxy_list(path: &std::path::Path) -> std::io::Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_robin_skips_unhealthy() {
+        let urls = vec![
+            "http://127.0.0.1:1".to_string(),
+            "socks5://127.0.0.1:2".to_string(),
+        ];
+        let pool = ProxyPool::new(&urls, Rotation::RoundRobin)
+            .unwrap()
+            .with_max_failures(2);
+
+        let first = pool.pick().unwrap();
+        let second = pool.pick().unwrap();
+        assert_ne!(first.index, second.index);
+
+        pool.report_failure(first);
+        pool.report_failure(first);
+        assert_eq!(pool.healthy(), [urls[1].as_str()]);
+        for _ in 0..4 {
+            assert_eq!(pool.pick().unwrap().index, second.index);
+        }
+
+        pool.report_failure(second);
+        pool.report_success(second);
+        pool.report_failure(second);
+        assert!(pool.pick().is_ok(), "success resets the failure streak");
+        pool.report_failure(second);
+        assert!(pool.pick().is_err());
+    }
+
+    #[test]
+    fn test_unhealthy_proxy_recovers() {
+        let urls = vec!["http://127.0.0.1:1".to_string()];
+        let pool = ProxyPool::new(&urls, Rotation::RoundRobin)
+            .unwrap()
+            .with_max_failures(1)
+            .with_recovery(Duration::from_millis(20));
+
+        let handle = pool.pick().unwrap();
+        pool.report_failure(handle);
+        assert!(pool.pick().is_err());
+
+        std::thread::sleep(Duration::from_millis(30));
+        let retried = pool.pick().expect("recovery time has passed");
+        pool.report_failure(retried);
+        assert!(pool.pick().is_err(), "a failed retry benches it again");
+
+        std::thread::sleep(Duration::from_millis(30));
+        pool.report_success(pool.pick().unwrap());
+        assert_eq!(pool.healthy(), [urls[0].as_str()]);
+    }
+
+    #[test]
+    fn test_read_proxy_list() {
+        let path = std::env::temp_dir().join("firststep_name_proxies.txt");
+        std::fs::write(
+            &path,
+            "# tor\nsocks5://127.0.0.1:9050\n\nhttp://10.0.0.1:8080\n",
+        )
+        .unwrap();
+
+        let proxies = read_proxy_list(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(proxies, ["socks5://127.0.0.1:9050", "http://10.0.0.1:8080"]);
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::{Instant, sleep};
+
+/// Longest a host is ever blocked, for cooldowns too large to add to `Instant`
+const MAX_BLOCK: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Politeness policy applied to every host contacted during a scan
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitPolicy {
+    /// Sustained requests per second allowed per host, `0` disables limiting
+    pub requests_per_second: f64,
+    /// Number of requests a host may receive back to back
+    pub burst: u32,
+    /// Pause applied to a host after it answers with 429 Too Many Requests
+    pub cooldown: Duration,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        RateLimitPolicy {
+            requests_per_second: 5.0,
+            burst: 5,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    blocked_until: Option<Instant>,
+}
+
+/// Token-bucket rate limiter keyed by host
+#[derive(Debug)]
+pub struct HostRateLimiter {
+    policy: RateLimitPolicy,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl HostRateLimiter {
+    pub fn new(policy: RateLimitPolicy) -> Self {
+        HostRateLimiter {
+            policy,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn policy(&self) -> &RateLimitPolicy {
+        &self.policy
+    }
+
+    /// Waits until a request to `host` is allowed by the policy
+    pub async fn acquire(&self, host: &str) {
+        if self.policy.requests_per_second <= 0.0 {
+            return;
+        }
+
+        loop {
+            match self.try_acquire(host) {
+                None => return,
+                Some(wait) => sleep(wait).await,
+            }
+        }
+    }
+
+    /// Takes a token for `host`, or returns how long to wait before retrying
+    fn try_acquire(&self, host: &str) -> Option<Duration> {
+        let now = Instant::now();
+        let burst = f64::from(self.policy.burst.max(1));
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(host.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+            blocked_until: None,
+        });
+
+        if let Some(until) = bucket.blocked_until {
+            if until > now {
+                return Some(until - now);
+            }
+            bucket.blocked_until = None;
+            bucket.updated = now;
+        }
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.policy.requests_per_second).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            let missing = 1.0 - bucket.tokens;
+            Some(Duration::from_secs_f64(
+                missing / self.policy.requests_per_second,
+            ))
+        }
+    }
+
+    /// Blocks `host` for the configured cooldown, e.g. after a 429 response
+    pub fn cool_down(&self, host: &str) {
+        self.cool_down_for(host, self.policy.cooldown);
+    }
+
+    /// Blocks `host` for `duration`, e.g. the server's `Retry-After` value,
+    /// but never longer than the configured cooldown
+    pub fn cool_down_for(&self, host: &str, duration: Duration) {
+        let now = Instant::now();
+        let duration = duration.min(self.policy.cooldown);
+        let until = now.checked_add(duration).unwrap_or_else(|| now + MAX_BLOCK);
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(host.to_string()).or_insert(Bucket {
+            tokens: 0.0,
+            updated: now,
+            blocked_until: None,
+        });
+        bucket.tokens = 0.0;
+        bucket.blocked_until = Some(until);
+    }
+}
+
+impl Default for HostRateLimiter {
+    fn default() -> Self {
+        HostRateLimiter::new(RateLimitPolicy::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_burst_then_refill() {
+        let limiter = HostRateLimiter::new(RateLimitPolicy {
+            requests_per_second: 10.0,
+            burst: 2,
+            cooldown: Duration::from_secs(1),
+        });
+
+        assert!(limiter.try_acquire("example.com").is_none());
+        assert!(limiter.try_acquire("example.com").is_none());
+        let wait = limiter.try_acquire("example.com").expect("bucket is empty");
+        assert!(wait <= Duration::from_millis(100));
+
+        // Other hosts have their own bucket
+        assert!(limiter.try_acquire("example.org").is_none());
+
+        let start = Instant::now();
+        limiter.acquire("example.com").await;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_cool_down_blocks_host() {
+        let limiter = HostRateLimiter::new(RateLimitPolicy {
+            requests_per_second: 100.0,
+            burst: 10,
+            cooldown: Duration::from_millis(200),
+        });
+
+        limiter.cool_down("example.com");
+        let wait = limiter
+            .try_acquire("example.com")
+            .expect("host is cooling down");
+        assert!(wait > Duration::from_millis(100));
+        assert!(limiter.try_acquire("example.org").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cool_down_for_is_capped() {
+        let limiter = HostRateLimiter::new(RateLimitPolicy {
+            requests_per_second: 100.0,
+            burst: 10,
+            cooldown: Duration::from_millis(200),
+        });
+
+        limiter.cool_down_for("example.com", Duration::MAX);
+        let wait = limiter
+            .try_acquire("example.com")
+            .expect("host is cooling down");
+        assert!(wait <= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_zero_rate_disables_limiting() {
+        let limiter = HostRateLimiter::new(RateLimitPolicy {
+            requests_per_second: 0.0,
+            ..RateLimitPolicy::default()
+        });
+
+        let start = Instant::now();
+        for _ in 0..100 {
+            limiter.acquire("example.com").await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+}
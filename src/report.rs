@@ -0,0 +1,747 @@
+use crate::{CheckError, CheckResult, Error, Verdict};
+use askama::Template;
+use chrono::{DateTime, Datelike, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Report file name used unless `--output-file` says otherwise
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{username}_report.{format}";
+
+/// Column order of CSV reports, kept stable for spreadsheets and scripts;
+/// new columns are only ever appended
+pub const CSV_HEADER: [&str; 9] = [
+    "site",
+    "category",
+    "verdict",
+    "http_status",
+    "url",
+    "profile_url",
+    "error",
+    "latency_ms",
+    "error_kind",
+];
+
+/// File formats the report writers support
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Txt,
+    Json,
+    Csv,
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    /// File extension, also substituted for `{format}` in file name templates
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Txt => "txt",
+            ReportFormat::Json => "json",
+            ReportFormat::Csv => "csv",
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+
+    /// Whether several usernames can be combined into one report of this format
+    pub fn supports_batch(&self) -> bool {
+        !matches!(self, ReportFormat::Html)
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "txt" => Ok(ReportFormat::Txt),
+            "json" => Ok(ReportFormat::Json),
+            "csv" => Ok(ReportFormat::Csv),
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            _ => Err(format!("Unsupported report format: {}", s)),
+        }
+    }
+}
+
+/// Where report files are written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportDestination {
+    /// File name template with `{username}`, `{date}` and `{format}`
+    /// placeholders, or `-` for stdout
+    pub template: String,
+    /// Directory the file name is relative to, created when missing
+    pub dir: Option<PathBuf>,
+    /// Add a timestamp suffix instead of overwriting an existing report
+    pub no_clobber: bool,
+}
+
+impl Default for ReportDestination {
+    fn default() -> Self {
+        ReportDestination {
+            template: DEFAULT_FILENAME_TEMPLATE.to_string(),
+            dir: None,
+            no_clobber: false,
+        }
+    }
+}
+
+impl ReportDestination {
+    pub fn is_stdout(&self) -> bool {
+        self.template == "-"
+    }
+
+    /// Path of the report for `username` before any `no_clobber` suffix,
+    /// `None` when writing to stdout
+    pub fn path_for(
+        &self,
+        username: &str,
+        format: ReportFormat,
+        now: DateTime<Local>,
+    ) -> Option<PathBuf> {
+        if self.is_stdout() {
+            return None;
+        }
+        let filename = self
+            .template
+            .replace("{username}", &file_name_safe(username))
+            .replace("{date}", &now.format("%Y-%m-%d").to_string())
+            .replace("{format}", format.extension());
+        Some(match &self.dir {
+            Some(dir) => dir.join(filename),
+            None => PathBuf::from(filename),
+        })
+    }
+
+    /// Opens the report for writing, returning the path it goes to unless
+    /// that is stdout
+    pub fn create(
+        &self,
+        username: &str,
+        format: ReportFormat,
+    ) -> io::Result<(Box<dyn Write>, Option<PathBuf>)> {
+        let now = Local::now();
+        let Some(path) = self.path_for(username, format, now) else {
+            return Ok((Box::new(io::stdout().lock()), None));
+        };
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        if !self.no_clobber {
+            return Ok((Box::new(File::create(&path)?), Some(path)));
+        }
+        let (file, path) = create_new_stamped(&path, now)?;
+        Ok((Box::new(file), Some(path)))
+    }
+}
+
+/// Keeps a username from escaping the report directory through the template
+fn file_name_safe(username: &str) -> String {
+    username.replace(['/', '\\'], "_").replace("..", "_")
+}
+
+/// Creates `path`, or the first free timestamped variant of it when it
+/// exists; `create_new` makes the check and the creation one step
+fn create_new_stamped(path: &Path, now: DateTime<Local>) -> io::Result<(File, PathBuf)> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let stamped = format!("{}-{}", stem, now.format("%Y%m%d-%H%M%S"));
+
+    let mut candidate = path.to_path_buf();
+    let mut counter = 1;
+    loop {
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(file) => return Ok((file, candidate)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+        candidate = match counter {
+            1 => path.with_file_name(format!("{}{}", stamped, extension)),
+            _ => path.with_file_name(format!("{}-{}{}", stamped, counter, extension)),
+        };
+        counter += 1;
+    }
+}
+
+/// Results of one scan as written to JSON reports
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Report {
+    pub username: String,
+    pub generated_at: String,
+    pub results: Vec<CheckResult>,
+}
+
+impl Report {
+    pub fn new(username: &str, results: Vec<CheckResult>) -> Self {
+        Report {
+            username: username.to_string(),
+            generated_at: chrono::Local::now().to_string(),
+            results,
+        }
+    }
+
+    /// Reads a report written by `save_json_report`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(io::BufReader::new(file))?)
+    }
+}
+
+pub(crate) fn count_verdicts(results: &[CheckResult], verdict: Verdict) -> usize {
+    results.iter().filter(|r| r.verdict == verdict).count()
+}
+
+/// Failed checks grouped by cause, in `CheckError::ALL` order. Failures
+/// without a recorded cause, e.g. from older reports, count as `Other`.
+pub fn group_failures(results: &[CheckResult]) -> BTreeMap<CheckError, Vec<&CheckResult>> {
+    let mut by_cause: BTreeMap<CheckError, Vec<&CheckResult>> = BTreeMap::new();
+    for result in results.iter().filter(|r| r.verdict == Verdict::Error) {
+        by_cause
+            .entry(result.error_kind.unwrap_or(CheckError::Other))
+            .or_default()
+            .push(result);
+    }
+    by_cause
+}
+
+/// Error message prefixed with its cause, as shown in reports
+fn error_text(result: &CheckResult) -> Option<String> {
+    let error = result.error.as_ref()?;
+    Some(match result.error_kind {
+        Some(kind) => format!("{}: {}", kind, error),
+        None => error.clone(),
+    })
+}
+
+/// Writes the report for `username` in `format` to `destination`, returning
+/// the path it was saved to unless it went to stdout
+pub fn save_report(
+    destination: &ReportDestination,
+    format: ReportFormat,
+    username: &str,
+    results: &[CheckResult],
+) -> Result<Option<PathBuf>, Error> {
+    let (mut file, path) = destination.create(username, format)?;
+    write_report(&mut file, format, username, results)?;
+    file.flush()?;
+    Ok(path)
+}
+
+/// Writes the report for `username` in `format`
+pub fn write_report(
+    file: &mut impl Write,
+    format: ReportFormat,
+    username: &str,
+    results: &[CheckResult],
+) -> Result<(), Error> {
+    match format {
+        ReportFormat::Txt => write_txt_report(file, username, results)?,
+        ReportFormat::Json => write_json_report(file, username, results)?,
+        ReportFormat::Csv => write_csv_report(file, results)?,
+        ReportFormat::Markdown => write_markdown_report(file, username, results)?,
+        ReportFormat::Html => write_html_report(file, username, results)?,
+    }
+    Ok(())
+}
+
+pub fn save_txt_report(username: &str, results: &[CheckResult]) -> Result<(), Error> {
+    save_report(
+        &ReportDestination::default(),
+        ReportFormat::Txt,
+        username,
+        results,
+    )?;
+    Ok(())
+}
+
+/// Writes the plain text report for one username
+pub fn write_txt_report(
+    file: &mut impl Write,
+    username: &str,
+    results: &[CheckResult],
+) -> io::Result<()> {
+    writeln!(file, "Username availability report for: {}", username)?;
+    writeln!(file, "Generated on: {}", chrono::Local::now())?;
+    let summary: Vec<String> = Verdict::ALL
+        .iter()
+        .map(|&verdict| format!("{}: {}", verdict, count_verdicts(results, verdict)))
+        .collect();
+    writeln!(file, "{}", summary.join(", "))?;
+    let failures: Vec<String> = group_failures(results)
+        .iter()
+        .map(|(kind, failed)| format!("{}: {}", kind, failed.len()))
+        .collect();
+    if !failures.is_empty() {
+        writeln!(file, "Failures by cause: {}", failures.join(", "))?;
+    }
+    writeln!(file, "{}", "-".repeat(80))?;
+
+    for result in results {
+        writeln!(file, "{}: {}", result.site, result.verdict)?;
+        writeln!(file, "URL: {}", result.profile_url)?;
+        if result.check_url != result.profile_url {
+            writeln!(file, "Check URL: {}", result.check_url)?;
+        }
+        writeln!(file, "Logo: {}", result.logo_url)?;
+        if result.attempts > 1 {
+            writeln!(file, "Attempts: {}", result.attempts)?;
+        }
+        if let Some(control) = result.control_verdict {
+            writeln!(file, "Control username: {}", control)?;
+        }
+        if let Some(error) = error_text(result) {
+            writeln!(file, "Error: {}", error)?;
+        }
+        writeln!(file, "{}", "-".repeat(40))?;
+    }
+
+    Ok(())
+}
+
+pub fn save_json_report(username: &str, results: &[CheckResult]) -> Result<(), Error> {
+    save_report(
+        &ReportDestination::default(),
+        ReportFormat::Json,
+        username,
+        results,
+    )?;
+    Ok(())
+}
+
+pub fn write_json_report(
+    file: &mut impl Write,
+    username: &str,
+    results: &[CheckResult],
+) -> Result<(), Error> {
+    let report = Report::new(username, results.to_vec());
+    serde_json::to_writer_pretty(&mut *file, &report)?;
+    writeln!(file)?;
+    Ok(())
+}
+
+pub fn save_csv_report(username: &str, results: &[CheckResult]) -> Result<(), Error> {
+    save_report(
+        &ReportDestination::default(),
+        ReportFormat::Csv,
+        username,
+        results,
+    )?;
+    Ok(())
+}
+
+/// Writes one row per site in `CSV_HEADER` order
+pub fn write_csv_report(file: &mut impl Write, results: &[CheckResult]) -> Result<(), Error> {
+    let mut writer = csv::Writer::from_writer(file);
+    writer.write_record(CSV_HEADER)?;
+    for result in results {
+        writer.write_record(csv_row(result))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The `CSV_HEADER` columns of one result
+pub(crate) fn csv_row(result: &CheckResult) -> [String; CSV_HEADER.len()] {
+    [
+        result.site.clone(),
+        result.category.clone(),
+        result.verdict.to_string(),
+        result
+            .http_status
+            .map(|s| s.to_string())
+            .unwrap_or_default(),
+        result.check_url.clone(),
+        result.profile_url.clone(),
+        result.error.clone().unwrap_or_default(),
+        result
+            .latency_ms
+            .map(|ms| ms.to_string())
+            .unwrap_or_default(),
+        result
+            .error_kind
+            .map(|kind| kind.key().to_string())
+            .unwrap_or_default(),
+    ]
+}
+
+pub fn save_markdown_report(username: &str, results: &[CheckResult]) -> Result<(), Error> {
+    save_report(
+        &ReportDestination::default(),
+        ReportFormat::Markdown,
+        username,
+        results,
+    )?;
+    Ok(())
+}
+
+/// Keeps cell text from breaking the table layout
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+/// Writes a summary table followed by one results table per category
+pub fn write_markdown_report(
+    file: &mut impl Write,
+    username: &str,
+    results: &[CheckResult],
+) -> io::Result<()> {
+    writeln!(file, "# Username availability report for `{}`", username)?;
+    writeln!(file)?;
+    writeln!(file, "Generated on: {}", chrono::Local::now())?;
+    writeln!(file)?;
+    writeln!(file, "| Verdict | Sites |")?;
+    writeln!(file, "| --- | ---: |")?;
+    for verdict in Verdict::ALL {
+        writeln!(
+            file,
+            "| {} | {} |",
+            verdict,
+            count_verdicts(results, verdict)
+        )?;
+    }
+
+    let failures = group_failures(results);
+    if !failures.is_empty() {
+        writeln!(file)?;
+        writeln!(file, "## Failures by cause")?;
+        writeln!(file)?;
+        writeln!(file, "| Cause | Sites |")?;
+        writeln!(file, "| --- | --- |")?;
+        for (kind, failed) in failures {
+            let sites: Vec<&str> = failed.iter().map(|r| r.site.as_str()).collect();
+            writeln!(file, "| {} | {} |", kind, markdown_cell(&sites.join(", ")))?;
+        }
+    }
+
+    for (category, results) in group_by_category(results) {
+        writeln!(file)?;
+        writeln!(file, "## {}", markdown_cell(category_heading(category)))?;
+        writeln!(file)?;
+        writeln!(file, "| Site | Verdict | Profile | Error |")?;
+        writeln!(file, "| --- | --- | --- | --- |")?;
+        for result in results {
+            writeln!(
+                file,
+                "| {} | {} | <{}> | {} |",
+                markdown_cell(&result.site),
+                result.verdict,
+                result.profile_url,
+                markdown_cell(&error_text(result).unwrap_or_default())
+            )?;
+        }
+    }
+    Ok(())
+}
+
+struct VerdictCount {
+    key: &'static str,
+    label: &'static str,
+    count: usize,
+}
+
+struct FailureGroup<'a> {
+    label: &'static str,
+    sites: Vec<&'a str>,
+}
+
+struct ReportSection<'a> {
+    category: &'a str,
+    results: Vec<&'a CheckResult>,
+}
+
+/// Single-file HTML report, styled like the web UI
+#[derive(Template)]
+#[template(ext = "html", path = "report.html")]
+struct ReportTemplate<'a> {
+    title: &'a str,
+    username: &'a str,
+    generated_at: String,
+    counts: Vec<VerdictCount>,
+    failures: Vec<FailureGroup<'a>>,
+    sections: Vec<ReportSection<'a>>,
+    year: i32,
+}
+
+/// Groups results by category in name order, uncategorized sites last
+fn group_by_category(results: &[CheckResult]) -> Vec<(&str, Vec<&CheckResult>)> {
+    let mut by_category: BTreeMap<&str, Vec<&CheckResult>> = BTreeMap::new();
+    for result in results {
+        by_category
+            .entry(result.category.as_str())
+            .or_default()
+            .push(result);
+    }
+    let mut groups: Vec<_> = by_category.into_iter().collect();
+    // The empty category sorts first in the map
+    if groups
+        .first()
+        .is_some_and(|(category, _)| category.is_empty())
+    {
+        groups.rotate_left(1);
+    }
+    groups
+}
+
+fn category_heading(category: &str) -> &str {
+    if category.is_empty() {
+        "uncategorized"
+    } else {
+        category
+    }
+}
+
+pub fn save_html_report(username: &str, results: &[CheckResult]) -> Result<(), Error> {
+    save_report(
+        &ReportDestination::default(),
+        ReportFormat::Html,
+        username,
+        results,
+    )?;
+    Ok(())
+}
+
+/// Renders a self-contained HTML page with per-category sections and verdict filters
+pub fn write_html_report(
+    file: &mut impl Write,
+    username: &str,
+    results: &[CheckResult],
+) -> Result<(), Error> {
+    let now = chrono::Local::now();
+    let title = format!("{} - First Step Name report", username);
+    let template = ReportTemplate {
+        title: &title,
+        username,
+        generated_at: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+        counts: Verdict::ALL
+            .iter()
+            .map(|&verdict| VerdictCount {
+                key: verdict.key(),
+                label: verdict.label(),
+                count: count_verdicts(results, verdict),
+            })
+            .filter(|count| count.count > 0)
+            .collect(),
+        failures: group_failures(results)
+            .into_iter()
+            .map(|(kind, failed)| FailureGroup {
+                label: kind.label(),
+                sites: failed.iter().map(|r| r.site.as_str()).collect(),
+            })
+            .collect(),
+        sections: group_by_category(results)
+            .into_iter()
+            .map(|(category, results)| ReportSection {
+                category: category_heading(category),
+                results,
+            })
+            .collect(),
+        year: now.year(),
+    };
+    template.write_into(file)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn result(site: &str, category: &str, verdict: Verdict) -> CheckResult {
+        CheckResult {
+            category: category.to_string(),
+            check_url: format!("https://api.example.com/{}", site),
+            ..test_util::result(site, verdict)
+        }
+    }
+
+    #[test]
+    fn test_write_csv_report() {
+        let mut failed = result("Broken, Inc", "misc", Verdict::Error);
+        failed.error = Some("operation timed out".to_string());
+        failed.error_kind = Some(CheckError::Timeout);
+        failed.http_status = None;
+        failed.latency_ms = None;
+        let results = [result("GitHub", "coding", Verdict::Taken), failed];
+
+        let mut out = Vec::new();
+        write_csv_report(&mut out, &results).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "site,category,verdict,http_status,url,profile_url,error,latency_ms,error_kind",
+                "GitHub,coding,Taken,200,https://api.example.com/GitHub,https://example.com/GitHub,,42,",
+                "\"Broken, Inc\",misc,Error,,\"https://api.example.com/Broken, Inc\",\"https://example.com/Broken, Inc\",operation timed out,,timeout",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_markdown_report_groups_by_category() {
+        let results = [
+            result("GitLab", "coding", Verdict::Available),
+            result("Orphan", "", Verdict::Taken),
+            result("Mastodon", "social", Verdict::Taken),
+            result("GitHub", "coding", Verdict::Taken),
+        ];
+
+        let mut out = Vec::new();
+        write_markdown_report(&mut out, "johndoe", &results).unwrap();
+        let markdown = String::from_utf8(out).unwrap();
+        assert!(markdown.contains("| Taken | 3 |"));
+        assert!(markdown.contains("| Available | 1 |"));
+
+        let coding = markdown.find("## coding").unwrap();
+        let social = markdown.find("## social").unwrap();
+        let github = markdown.find("| GitHub | Taken |").unwrap();
+        let uncategorized = markdown.find("## uncategorized").unwrap();
+        assert!(coding < github && github < social && social < uncategorized);
+        assert!(markdown.find("| GitLab |").unwrap() < github);
+    }
+
+    #[test]
+    fn test_group_failures() {
+        let failed = |site, kind| {
+            let mut failed = result(site, "misc", Verdict::Error);
+            failed.error = Some("failed".to_string());
+            failed.error_kind = kind;
+            failed
+        };
+        let results = [
+            failed("Reddit", Some(CheckError::Tls)),
+            result("GitHub", "coding", Verdict::Taken),
+            failed("MySpace", None),
+            failed("Mastodon", Some(CheckError::Timeout)),
+            failed("Bluesky", Some(CheckError::Timeout)),
+        ];
+
+        let groups: Vec<(CheckError, Vec<&str>)> = group_failures(&results)
+            .into_iter()
+            .map(|(kind, failed)| (kind, failed.iter().map(|r| r.site.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (CheckError::Timeout, vec!["Mastodon", "Bluesky"]),
+                (CheckError::Tls, vec!["Reddit"]),
+                (CheckError::Other, vec!["MySpace"]),
+            ]
+        );
+
+        let mut out = Vec::new();
+        write_txt_report(&mut out, "johndoe", &results).unwrap();
+        let txt = String::from_utf8(out).unwrap();
+        assert!(txt.contains("Failures by cause: Timeout: 2, TLS failure: 1, Other error: 1"));
+        assert!(txt.contains("Error: TLS failure: failed"));
+    }
+
+    #[test]
+    fn test_write_html_report() {
+        let mut taken = result("GitHub", "coding", Verdict::Taken);
+        taken.profile_url = "https://github.com/<script>".to_string();
+        let results = [taken, result("Mastodon", "social", Verdict::Available)];
+
+        let mut out = Vec::new();
+        write_html_report(&mut out, "johndoe", &results).unwrap();
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.contains("--primary-color"), "style should be inlined");
+        assert!(html.contains(r#"<h2 class="category">coding</h2>"#));
+        assert!(html.contains(r#"value="available" checked"#));
+        assert!(
+            !html.contains(r#"value="error""#),
+            "empty verdicts get no filter"
+        );
+        assert!(html.contains("https://github.com/&#60;script&#62;"));
+    }
+
+    #[test]
+    fn test_destination_path_for() {
+        let now = chrono::Local::now();
+        let date = now.format("%Y-%m-%d").to_string();
+
+        let default = ReportDestination::default();
+        assert_eq!(
+            default.path_for("johndoe", ReportFormat::Markdown, now),
+            Some(PathBuf::from("johndoe_report.md"))
+        );
+
+        let dir = std::env::temp_dir().join("firststep_name_reports");
+        fs::create_dir_all(&dir).unwrap();
+        let mut destination = ReportDestination {
+            template: "{username}-{date}.{format}".to_string(),
+            dir: Some(dir.clone()),
+            no_clobber: false,
+        };
+        let path = dir.join(format!("johndoe-{}.json", date));
+        assert_eq!(
+            destination.path_for("johndoe", ReportFormat::Json, now),
+            Some(path.clone())
+        );
+
+        destination.template = "{username}.{format}".to_string();
+        assert_eq!(
+            destination.path_for("../etc/passwd", ReportFormat::Json, now),
+            Some(dir.join("__etc_passwd.json")),
+            "usernames cannot leave the report directory"
+        );
+
+        let stdout = ReportDestination {
+            template: "-".to_string(),
+            ..ReportDestination::default()
+        };
+        assert_eq!(stdout.path_for("johndoe", ReportFormat::Txt, now), None);
+    }
+
+    #[test]
+    fn test_destination_no_clobber() {
+        let dir = std::env::temp_dir().join("firststep_name_no_clobber");
+        let _ = fs::remove_dir_all(&dir);
+        let mut destination = ReportDestination {
+            template: "{username}.{format}".to_string(),
+            dir: Some(dir.clone()),
+            no_clobber: false,
+        };
+        let path = dir.join("johndoe.txt");
+        let write = |destination: &ReportDestination, text: &str| {
+            let (mut file, written) = destination.create("johndoe", ReportFormat::Txt).unwrap();
+            file.write_all(text.as_bytes()).unwrap();
+            written.unwrap()
+        };
+
+        assert_eq!(write(&destination, "first"), path);
+        assert_eq!(
+            write(&destination, "second"),
+            path,
+            "overwritten by default"
+        );
+        destination.no_clobber = true;
+        let third = write(&destination, "third");
+        let fourth = write(&destination, "fourth");
+        assert_ne!(third, path);
+        assert_ne!(fourth, third);
+        assert!(
+            third
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("johndoe-")
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_to_string(&third).unwrap(), "third");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+use crate::CheckError;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// How often and how patiently transient failures are retried
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of requests per site, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every further attempt
+    pub base_delay: Duration,
+    /// Upper bound for a single delay; longer `Retry-After` values give up instead
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that sends every request exactly once
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Jittered exponential backoff after the given (1-based) failed attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        // Equal jitter: keep half of the delay, randomize the other half
+        delay / 2 + delay.mul_f64(rand::random_range(0.0..0.5))
+    }
+
+    /// Delay before the next attempt, or `None` when no retry should be made
+    pub fn next_delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        match retry_after {
+            Some(delay) if delay > self.max_delay => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+}
+
+/// Status codes worth retrying: rate limits and temporary upstream failures
+pub fn is_transient_status(status: u16) -> bool {
+    matches!(status, 429 | 502 | 503 | 504)
+}
+
+/// Errors worth retrying: timeouts, refused or reset connections, cut bodies
+/// and failed proxies, which rotation replaces. DNS and TLS failures are not
+/// going to fix themselves within a scan.
+pub fn is_transient_error(error: &reqwest::Error, via_proxy: bool) -> bool {
+    error.is_body()
+        || matches!(
+            CheckError::classify(error, via_proxy),
+            CheckError::Timeout | CheckError::Connect | CheckError::Proxy
+        )
+}
+
+/// Parses a `Retry-After` header given either in seconds or as an HTTP date
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        date.with_timezone(&Utc)
+            .signed_duration_since(now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+
+        let first = policy.backoff(1);
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+        let third = policy.backoff(3);
+        assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+        assert!(policy.backoff(30) <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_next_delay_respects_limits() {
+        let policy = RetryPolicy::default();
+
+        assert!(policy.next_delay(1, None).is_some());
+        assert_eq!(policy.next_delay(3, None), None);
+        assert_eq!(
+            policy.next_delay(1, Some(Duration::from_secs(2))),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(policy.next_delay(1, Some(Duration::from_secs(3600))), None);
+        assert_eq!(RetryPolicy::none().next_delay(1, None), None);
+    }
+
+    #[tokio::test]
+    async fn test_is_transient_error() {
+        let client = reqwest::Client::new();
+        let refused = client.get("http://127.0.0.1:1/").send().await.unwrap_err();
+        assert!(is_transient_error(&refused, false));
+        let unresolved = client
+            .get("http://firststep-name.invalid/")
+            .send()
+            .await
+            .unwrap_err();
+        assert!(!is_transient_error(&unresolved, false));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
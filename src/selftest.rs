@@ -0,0 +1,200 @@
+use crate::{CheckOptions, SiteData, Verdict, check_one, random_username};
+use futures_util::stream::{self, StreamExt};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Overall state of a site definition after the self-test
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SiteHealthStatus {
+    /// Every probe produced the expected verdict
+    Healthy,
+    /// At least one probe contradicted the `e_*`/`m_*` rules
+    Broken,
+    /// Errors, challenges or invalid names kept the rules from being verified
+    Unverified,
+}
+
+/// A single probe of the self-test
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProbeResult {
+    pub username: String,
+    pub expected: Verdict,
+    pub verdict: Verdict,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SiteHealth {
+    pub site: String,
+    pub status: SiteHealthStatus,
+    pub probes: Vec<ProbeResult>,
+}
+
+/// Machine-readable result of `sites selftest`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HealthReport {
+    pub generated_at: String,
+    pub healthy: usize,
+    pub broken: usize,
+    pub unverified: usize,
+    pub sites: Vec<SiteHealth>,
+}
+
+impl HealthReport {
+    /// Names of the sites whose rules no longer hold
+    pub fn broken_sites(&self) -> impl Iterator<Item = &str> {
+        self.sites
+            .iter()
+            .filter(|site| site.status == SiteHealthStatus::Broken)
+            .map(|site| site.site.as_str())
+    }
+}
+
+fn site_status(probes: &[ProbeResult]) -> SiteHealthStatus {
+    let evaluated = |probe: &&ProbeResult| {
+        matches!(
+            probe.verdict,
+            Verdict::Taken | Verdict::Available | Verdict::Inconclusive
+        )
+    };
+    if probes
+        .iter()
+        .filter(evaluated)
+        .any(|probe| probe.verdict != probe.expected)
+    {
+        SiteHealthStatus::Broken
+    } else if probes.iter().all(|probe| probe.verdict == probe.expected) {
+        SiteHealthStatus::Healthy
+    } else {
+        SiteHealthStatus::Unverified
+    }
+}
+
+/// Checks every site with up to `known_per_site` of its `known` accounts,
+/// which must come back taken, and with a random username, which must come
+/// back available.
+pub async fn selftest_sites(
+    client: &Client,
+    sites_data: &[SiteData],
+    options: &CheckOptions,
+    known_per_site: usize,
+) -> HealthReport {
+    let control = random_username();
+    let matcher = options.filter.matcher();
+    let mut probes = Vec::new();
+    for (index, site) in sites_data.iter().enumerate() {
+        if !matcher.matches(site) {
+            continue;
+        }
+        for known in site.known.iter().take(known_per_site) {
+            probes.push((index, known.clone(), Verdict::Taken));
+        }
+        probes.push((index, control.clone(), Verdict::Available));
+    }
+
+    let concurrency = options.concurrency.max(1);
+    let results: Vec<(usize, ProbeResult)> = stream::iter(probes)
+        .map(|(index, username, expected)| async move {
+            let result = check_one(client, &sites_data[index], &username, options).await;
+            let probe = ProbeResult {
+                username,
+                expected,
+                verdict: result.verdict,
+                error: result.error,
+            };
+            (index, probe)
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+
+    let mut by_site: BTreeMap<usize, Vec<ProbeResult>> = BTreeMap::new();
+    for (index, probe) in results {
+        by_site.entry(index).or_default().push(probe);
+    }
+
+    let sites: Vec<SiteHealth> = by_site
+        .into_iter()
+        .map(|(index, probes)| SiteHealth {
+            site: sites_data[index].name.clone(),
+            status: site_status(&probes),
+            probes,
+        })
+        .collect();
+    let count = |status| sites.iter().filter(|site| site.status == status).count();
+
+    HealthReport {
+        generated_at: chrono::Local::now().to_string(),
+        healthy: count(SiteHealthStatus::Healthy),
+        broken: count(SiteHealthStatus::Broken),
+        unverified: count(SiteHealthStatus::Unverified),
+        sites,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{always_found, profile, serve, test_site};
+    use poem::{Route, get};
+
+    #[tokio::test]
+    async fn test_selftest_sites() {
+        let base = serve(
+            Route::new()
+                .at("/good/:name", get(profile))
+                .at("/broken/:name", get(always_found)),
+        )
+        .await;
+        let mut good = test_site(format!("{}/good/{{account}}", base));
+        good.name = "Good".to_string();
+        let mut broken = test_site(format!("{}/broken/{{account}}", base));
+        broken.name = "Broken".to_string();
+
+        let sites = [good, broken];
+        let report = selftest_sites(&Client::new(), &sites, &CheckOptions::default(), 1).await;
+
+        assert_eq!(
+            (report.healthy, report.broken, report.unverified),
+            (1, 1, 0)
+        );
+        assert_eq!(report.sites[0].site, "Good");
+        assert_eq!(report.sites[0].probes.len(), 2);
+        assert_eq!(report.broken_sites().collect::<Vec<_>>(), ["Broken"]);
+    }
+
+    fn probe(expected: Verdict, verdict: Verdict) -> ProbeResult {
+        ProbeResult {
+            username: "johndoe".to_string(),
+            expected,
+            verdict,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_site_status() {
+        let healthy = [
+            probe(Verdict::Taken, Verdict::Taken),
+            probe(Verdict::Available, Verdict::Available),
+        ];
+        assert_eq!(site_status(&healthy), SiteHealthStatus::Healthy);
+
+        let false_positive = [
+            probe(Verdict::Taken, Verdict::Taken),
+            probe(Verdict::Available, Verdict::Taken),
+        ];
+        assert_eq!(site_status(&false_positive), SiteHealthStatus::Broken);
+
+        let drifted = [probe(Verdict::Taken, Verdict::Inconclusive)];
+        assert_eq!(site_status(&drifted), SiteHealthStatus::Broken);
+
+        let blocked = [
+            probe(Verdict::Taken, Verdict::Blocked),
+            probe(Verdict::Available, Verdict::Available),
+        ];
+        assert_eq!(site_status(&blocked), SiteHealthStatus::Unverified);
+    }
+}
@@ -1,18 +1,38 @@
 use crate::templates::{HelloTemplate, IndexTemplate};
 use askama::Template;
 use chrono::Datelike;
-use firststep_name_lib::{SitesFile, check_username_from_webserver, download_sites_data};
+use firststep_name_lib::history::SiteTimeline;
+use firststep_name_lib::{
+    CheckOptions, CheckResult, CompletionWebhooks, Error, HistoryStore, Report, SiteFilter,
+    SitesFile, WebSocketSink, build_client, check_username_with_sink, download_sites_data,
+};
 use futures_util::StreamExt;
 use poem::{
     Endpoint, EndpointExt, IntoResponse, Route, Server, endpoint::StaticFilesEndpoint, get,
-    handler, http::StatusCode, listener::TcpListener, web::Data, web::Html, web::Path,
-    web::websocket::WebSocket,
+    handler, http::StatusCode, listener::TcpListener, web::Data, web::Html, web::Json, web::Path,
+    web::Query, web::websocket::WebSocket,
 };
 use reqwest::Client;
+use serde::Deserialize;
 use serde_json::Value;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::Mutex;
+
+/// Settings for the web server
+#[derive(Debug, Clone, Default)]
+pub struct ServerConfig {
+    /// Base options of every WebSocket scan; the query string only changes
+    /// the site selection, and the rate limiter is shared by all scans
+    pub options: CheckOptions,
+    /// Proxy URLs, the first of which downloads the sites data
+    pub proxies: Vec<String>,
+    /// Sites to check instead of downloading the data file at startup
+    pub sites_data: Option<Arc<SitesFile>>,
+    /// SQLite file recording every WebSocket scan, `None` to disable history
+    pub history: Option<PathBuf>,
+    /// Endpoints told about every finished WebSocket scan
+    pub webhooks: CompletionWebhooks,
+}
 
 #[handler]
 fn hello(Path(name): Path<String>) -> impl IntoResponse {
@@ -34,34 +54,114 @@ fn ok() -> impl IntoResponse {
     "ok"
 }
 
+/// Site selection accepted by `/ws/:username`, lists are comma separated
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ScanQuery {
+    category: Option<String>,
+    exclude_category: Option<String>,
+    site: Option<String>,
+    exclude_site: Option<String>,
+    nsfw: bool,
+}
+
+impl ScanQuery {
+    fn into_filter(self, base: &SiteFilter) -> SiteFilter {
+        SiteFilter {
+            categories: split_list(self.category),
+            exclude_categories: split_list(self.exclude_category),
+            sites: split_list(self.site),
+            exclude_sites: split_list(self.exclude_site),
+            nsfw: self.nsfw,
+            skip_protected: base.skip_protected,
+        }
+    }
+}
+
+/// What happens once a WebSocket scan has checked every site
+struct ScanFinish {
+    history: Option<Arc<HistoryStore>>,
+    webhooks: CompletionWebhooks,
+}
+
+impl ScanFinish {
+    async fn run(&self, username: &str, results: Vec<CheckResult>) {
+        if let Some(store) = &self.history
+            && let Err(e) = store.record(username, &results, chrono::Utc::now())
+        {
+            eprintln!("Failed to record history for {}: {}", username, e);
+        }
+        self.webhooks.deliver(&Report::new(username, results)).await;
+    }
+}
+
 #[handler]
 fn ws_handler(
     Path(username): Path<String>,
+    Query(query): Query<ScanQuery>,
     ws: WebSocket,
     client: Data<&Client>,
     sites_data: Data<&Arc<SitesFile>>,
+    options: Data<&CheckOptions>,
+    finish: Data<&Arc<ScanFinish>>,
 ) -> impl IntoResponse {
     let client = client.clone();
     let sites_data = sites_data.clone();
+    let finish = finish.clone();
+    let mut options = options.clone();
+    options.filter = query.into_filter(&options.filter);
 
     ws.on_upgrade(move |socket| async move {
         println!("WebSocket connected for username: {}", username);
         let (sink, _stream) = socket.split();
-        let sink = Arc::new(Mutex::new(sink));
+        let sink = WebSocketSink::new(sink);
 
         tokio::spawn(async move {
-            let _results = check_username_from_webserver(
-                &client,
-                &username,
-                &sites_data.sites,
-                1, // Use a single thread for web server checks
-                Some(sink),
-            )
-            .await;
+            let results =
+                check_username_with_sink(&client, &username, &sites_data.sites, &options, &sink)
+                    .await;
+            finish.run(&username, results).await;
         });
     })
 }
 
+/// Site selection accepted by `/history/:username`, site globs or `/regex/`
+/// patterns are comma separated
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct HistoryQuery {
+    site: Option<String>,
+}
+
+/// Splits a comma separated query value into trimmed, non-empty items
+fn split_list(value: Option<String>) -> Vec<String> {
+    value
+        .iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[handler]
+fn history_handler(
+    Path(username): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    history: Data<&Option<Arc<HistoryStore>>>,
+) -> Result<Json<Vec<SiteTimeline>>, StatusCode> {
+    let Some(store) = history.as_ref() else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    store
+        .timeline(&username, &split_list(query.site))
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("Failed to read history for {}: {}", username, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
 #[handler]
 async fn index_get() -> impl IntoResponse {
     let template = IndexTemplate {
@@ -92,37 +192,48 @@ async fn fetch_json() -> Result<poem::web::Json<Value>, StatusCode> {
     }
 }
 
-async fn app() -> impl Endpoint {
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
-        .timeout(Duration::from_secs(30))
-        .build()
-        .unwrap();
+/// Builds the routes, failing on an invalid proxy URL or history database
+async fn app(config: ServerConfig) -> Result<impl Endpoint, Error> {
+    let client = build_client(config.proxies.first().map(String::as_str))?;
 
-    let sites_data: Arc<SitesFile> = Arc::new({
-        let json_file = "social_sites.json";
-        if let Err(e) = download_sites_data(&client, json_file).await {
-            eprintln!("Failed to download sites data: {}", e);
+    let sites_data = match config.sites_data {
+        Some(sites_data) => sites_data,
+        None => {
+            let json_file = "social_sites.json";
+            if let Err(e) = download_sites_data(&client, json_file).await {
+                eprintln!("Failed to download sites data: {}", e);
+            }
+            Arc::new(SitesFile::load(json_file)?)
         }
-        let file = std::fs::File::open(json_file).expect("Failed to open sites data file");
-        let reader = std::io::BufReader::new(file);
-        serde_json::from_reader(reader).expect("Failed to parse sites data")
-    });
+    };
 
-    Route::new()
+    let history = config
+        .history
+        .as_ref()
+        .map(HistoryStore::open)
+        .transpose()?
+        .map(Arc::new);
+    Ok(Route::new()
         .at("/hello/:name", get(hello))
         .at("/is_ok", get(ok))
         .at("/", get(index_get))
         .at("/ws/:username", get(ws_handler))
+        .at("/history/:username", get(history_handler))
         .at("/fetch_json", get(fetch_json))
         .nest("/static", StaticFilesEndpoint::new("./static"))
         .data(client)
         .data(sites_data)
+        .data(config.options)
+        .data(history.clone())
+        .data(Arc::new(ScanFinish {
+            history,
+            webhooks: config.webhooks,
+        })))
 }
 
 /// Starts the web server to handle requests
-pub async fn run_server() -> Result<(), std::io::Error> {
-    let app = app().await;
+pub async fn run_server(config: ServerConfig) -> Result<(), Error> {
+    let app = app(config).await?;
     println!("Starting server on http://127.0.0.1:3003");
     Server::new(TcpListener::bind("0.0.0.0:3003"))
         .run_with_graceful_shutdown(
@@ -133,14 +244,18 @@ pub async fn run_server() -> Result<(), std::io::Error> {
             },
             None,
         )
-        .await
+        .await?;
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
+    use firststep_name_lib::{Checker, SiteData, Verdict};
     use poem::test::TestClient;
 
+    #[allow(dead_code)] // used by the disabled `test_run_server`
     fn send_ctrl_c_signal() {
         let pid = std::process::id() as i32;
 
@@ -159,17 +274,171 @@ mod tests {
 
     // #[tokio::test]
     // async fn test_run_server() {
-    //     let server_handle = tokio::spawn(async { run_server().await });
+    //     let server_handle = tokio::spawn(async { run_server(ServerConfig::default()).await });
     //     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
     //     send_ctrl_c_signal();
     //     let result = server_handle.await.expect("Server task panicked");
     //     assert!(result.is_ok(), "Server failed to start: {:?}", result.err());
     // }
 
+    #[tokio::test]
+    async fn test_ws_scan_keeps_skip_protected() {
+        let target = serve(Route::new().at("/:name", get(profile))).await;
+        let site = |name: &str| {
+            SiteData::new(
+                name,
+                format!("{}/{{account}}", target),
+                200,
+                "\"id\":",
+                404,
+                "Not Found",
+            )
+            .with_category("coding")
+        };
+        let sites = vec![
+            site("GitHub"),
+            site("GitLab").with_protection(vec!["cloudflare".to_string()]),
+            site("Gitea"),
+        ];
+        let config = ServerConfig {
+            options: CheckOptions {
+                filter: SiteFilter {
+                    skip_protected: true,
+                    ..SiteFilter::default()
+                },
+                ..CheckOptions::default()
+            },
+            sites_data: Some(Arc::new(SitesFile::new(sites))),
+            ..ServerConfig::default()
+        };
+        let base = serve(app(config).await.unwrap()).await;
+
+        let url = format!("{}/ws/johndoe?site=git*", base.replacen("http", "ws", 1));
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        let mut checked = Vec::new();
+        while let Some(message) = socket.next().await {
+            let text = message.unwrap().into_text().unwrap();
+            let update: Value = serde_json::from_str(&text).unwrap();
+            if update["completed"] == true {
+                break;
+            }
+            checked.push(update["site"].as_str().unwrap().to_string());
+        }
+        checked.sort();
+        assert_eq!(
+            checked,
+            ["GitHub", "Gitea"],
+            "the protected site is skipped"
+        );
+    }
+
+    #[test]
+    fn test_scan_query_into_filter() {
+        let query = ScanQuery {
+            category: Some("social, coding".to_string()),
+            exclude_site: Some("git*".to_string()),
+            ..ScanQuery::default()
+        };
+        let base = SiteFilter {
+            skip_protected: true,
+            ..SiteFilter::default()
+        };
+
+        let filter = query.into_filter(&base);
+        assert_eq!(filter.categories, ["social", "coding"]);
+        assert_eq!(filter.exclude_sites, ["git*"]);
+        assert!(filter.sites.is_empty());
+        assert!(!filter.nsfw);
+        assert!(filter.skip_protected);
+    }
+
+    /// Serves `app` on a random local port and returns its base URL
+    async fn serve(app: impl Endpoint + 'static) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let acceptor = poem::listener::TcpAcceptor::from_tokio(listener).unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(app));
+        format!("http://{}", addr)
+    }
+
+    #[handler]
+    fn profile(Path(name): Path<String>) -> poem::Response {
+        if name == "johndoe" {
+            r#"{"id": 1}"#.into_response()
+        } else {
+            "Not Found"
+                .with_status(StatusCode::NOT_FOUND)
+                .into_response()
+        }
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_history() {
+        let base = serve(Route::new().at("/:name", get(profile))).await;
+        let sites: Vec<SiteData> = ["GitHub", "GitLab", "Mastodon"]
+            .into_iter()
+            .map(|name| {
+                let uri = format!("{}/{{account}}", base);
+                SiteData::new(name, uri, 200, "\"id\":", 404, "Not Found")
+            })
+            .collect();
+        let checker = Checker::builder().build().unwrap();
+
+        let path = std::env::temp_dir().join("firststep_name_server_history.db");
+        let _ = std::fs::remove_file(&path);
+        let store = HistoryStore::open(&path).unwrap();
+        let day = |d| chrono::Utc.with_ymd_and_hms(2025, 3, d, 12, 0, 0).unwrap();
+        let taken = checker.check("johndoe", &sites).await;
+        store.record("johndoe", &taken, day(1)).unwrap();
+        let free = checker.check("janedoe", &sites).await;
+        store.record("johndoe", &free, day(2)).unwrap();
+        drop(store);
+
+        let config = ServerConfig {
+            history: Some(path.clone()),
+            ..ServerConfig::default()
+        };
+        let cli = TestClient::new(app(config).await.unwrap());
+        let res = cli
+            .get("/history/johndoe")
+            .query("site", &"git*")
+            .send()
+            .await;
+        res.assert_status_is_ok();
+        let body = res.0.into_body().into_string().await.unwrap();
+        let timelines: Vec<SiteTimeline> = serde_json::from_str(&body).unwrap();
+        let summary: Vec<_> = timelines
+            .iter()
+            .map(|timeline| {
+                let entries: Vec<_> = timeline
+                    .entries
+                    .iter()
+                    .map(|e| (e.verdict, e.http_status, e.checked_at.as_str()))
+                    .collect();
+                (timeline.site.as_str(), entries)
+            })
+            .collect();
+        let history = vec![
+            (Verdict::Taken, Some(200), "2025-03-01T12:00:00Z"),
+            (Verdict::Available, Some(404), "2025-03-02T12:00:00Z"),
+        ];
+        assert_eq!(
+            summary,
+            [("GitHub", history.clone()), ("GitLab", history)],
+            "the glob leaves Mastodon out"
+        );
+
+        let cli = TestClient::new(app(ServerConfig::default()).await.unwrap());
+        let res = cli.get("/history/johndoe").send().await;
+        res.assert_status(StatusCode::NOT_FOUND);
+        std::fs::remove_file(path).unwrap();
+    }
+
     #[tokio::test]
     #[serial]
     async fn test_is_ok() {
-        let cli = TestClient::new(app().await);
+        let cli = TestClient::new(app(ServerConfig::default()).await.unwrap());
         let respo = cli.get("/is_ok").send().await;
         respo.assert_status_is_ok();
         respo.assert_content_type("text/plain; charset=utf-8");
@@ -179,7 +448,7 @@ mod tests {
     #[tokio::test]
     #[serial]
     async fn test_index() {
-        let cli = TestClient::new(app().await);
+        let cli = TestClient::new(app(ServerConfig::default()).await.unwrap());
 
         let res = cli.get("/").send().await;
         res.assert_status_is_ok();
@@ -195,7 +464,7 @@ mod tests {
     #[tokio::test]
     #[serial]
     async fn test_hello() {
-        let cli = TestClient::new(app().await);
+        let cli = TestClient::new(app(ServerConfig::default()).await.unwrap());
 
         let name = "suczkom";
         let res = cli.get(format!("/hello/{}", name)).send().await;
@@ -213,7 +482,7 @@ mod tests {
     #[tokio::test]
     #[serial]
     async fn test_fetch_json() {
-        let cli = TestClient::new(app().await);
+        let cli = TestClient::new(app(ServerConfig::default()).await.unwrap());
 
         let res = cli.get("/fetch_json").send().await;
         res.assert_status_is_ok();
@@ -0,0 +1,169 @@
+use crate::{CheckResult, ProgressUpdate};
+use colored::*;
+use futures_util::sink::SinkExt;
+use futures_util::stream::SplitSink;
+use poem::web::websocket::{Message, WebSocketStream};
+use serde::Serialize;
+use std::io::Write;
+use std::sync::Mutex;
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+/// Receives per-site updates from the checking engine.
+///
+/// Callbacks are synchronous and must not block; sinks that talk to slow
+/// consumers should hand the update off to a channel, like `WebSocketSink`.
+pub trait ProgressSink: Send + Sync {
+    /// Called once before the first request is sent
+    fn on_start(&self, _username: &str, _total: usize) {}
+
+    /// Called as soon as a single site check completes
+    fn on_result(&self, result: &CheckResult, completed: usize, total: usize);
+
+    /// Called once after every site has been checked
+    fn on_complete(&self, _username: &str, _results: &[CheckResult]) {}
+}
+
+/// Discards every update, for callers that only want the returned results
+pub struct NullSink;
+
+impl ProgressSink for NullSink {
+    fn on_result(&self, _result: &CheckResult, _completed: usize, _total: usize) {}
+}
+
+/// Prints colored per-site lines to stdout
+pub struct ConsoleSink;
+
+/// Colored line printed for a single result
+fn console_line(result: &CheckResult) -> String {
+    let label = result.verdict.label().color(result.verdict.color());
+    match &result.error {
+        Some(err) => format!("{} {} - {}", label, result.site, err),
+        None => format!("{} {} - {}", label, result.site, result.profile_url),
+    }
+}
+
+impl ProgressSink for ConsoleSink {
+    fn on_start(&self, username: &str, _total: usize) {
+        println!("Checking availability for username: {}\n", username);
+    }
+
+    fn on_result(&self, result: &CheckResult, _completed: usize, _total: usize) {
+        println!("{}", console_line(result));
+    }
+}
+
+/// Prints the same lines as `ConsoleSink` to stderr, keeping stdout free for
+/// a report
+pub struct StderrSink;
+
+impl ProgressSink for StderrSink {
+    fn on_start(&self, username: &str, _total: usize) {
+        eprintln!("Checking availability for username: {}\n", username);
+    }
+
+    fn on_result(&self, result: &CheckResult, _completed: usize, _total: usize) {
+        eprintln!("{}", console_line(result));
+    }
+}
+
+/// Writes every result as one JSON line to stdout as soon as it completes
+#[derive(Default)]
+pub struct NdjsonSink {
+    username: Mutex<String>,
+}
+
+#[derive(Serialize)]
+struct NdjsonLine<'a> {
+    username: &'a str,
+    #[serde(flatten)]
+    result: &'a CheckResult,
+}
+
+impl ProgressSink for NdjsonSink {
+    fn on_start(&self, username: &str, _total: usize) {
+        *self.username.lock().unwrap() = username.to_string();
+    }
+
+    fn on_result(&self, result: &CheckResult, _completed: usize, _total: usize) {
+        let username = self.username.lock().unwrap();
+        let line = NdjsonLine {
+            username: &username,
+            result,
+        };
+        let Ok(json) = serde_json::to_string(&line) else {
+            return;
+        };
+        // Flush per line so consumers see partial results of interrupted scans
+        let mut stdout = std::io::stdout().lock();
+        if writeln!(stdout, "{}", json)
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            eprintln!("Failed to write result for {}", result.site);
+        }
+    }
+}
+
+/// Streams `ProgressUpdate` messages to a poem WebSocket
+pub struct WebSocketSink {
+    tx: UnboundedSender<Message>,
+}
+
+impl WebSocketSink {
+    /// Spawns a task that forwards updates to `ws_sink` in order
+    pub fn new(mut ws_sink: SplitSink<WebSocketStream, Message>) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                if ws_sink.send(message).await.is_err() {
+                    eprintln!("Failed to send WebSocket message");
+                    break;
+                }
+            }
+        });
+        WebSocketSink { tx }
+    }
+
+    fn send(&self, text: String) {
+        // The receiver only goes away once the socket has failed
+        let _ = self.tx.send(Message::Text(text));
+    }
+}
+
+impl ProgressSink for WebSocketSink {
+    fn on_result(&self, result: &CheckResult, completed: usize, total: usize) {
+        let update = ProgressUpdate::new(result, completed, total);
+        if let Ok(json) = serde_json::to_string(&update) {
+            self.send(json);
+        }
+    }
+
+    fn on_complete(&self, _username: &str, results: &[CheckResult]) {
+        let completion_msg = serde_json::json!({
+            "completed": true,
+            "total": results.len()
+        })
+        .to_string();
+        self.send(completion_msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Verdict;
+    use crate::test_util::result;
+
+    #[test]
+    fn test_ndjson_line_flattens_result() {
+        let result = result("GitHub", Verdict::Taken);
+        let line = NdjsonLine {
+            username: "johndoe",
+            result: &result,
+        };
+        let json: serde_json::Value = serde_json::to_value(&line).unwrap();
+        assert_eq!(json["username"], "johndoe");
+        assert_eq!(json["site"], "GitHub");
+        assert_eq!(json["verdict"], "taken");
+    }
+}
@@ -9,12 +9,14 @@ pub struct HelloTemplate<'a> {
     pub year: i32, // Current year
 }
 
+#[allow(dead_code)] // rendered through `{% include %}`
 #[derive(Template)]
 #[template(ext = "html", path = "head.html")]
 pub struct HeadTemplate<'a> {
     pub title: &'a str,
 }
 
+#[allow(dead_code)] // rendered through `{% include %}`
 #[derive(Template)]
 #[template(ext = "html", path = "footer.html")]
 pub struct FooterTemplate {
@@ -28,10 +30,12 @@ pub struct IndexTemplate<'a> {
     pub year: i32, // Current year
 }
 
+#[allow(dead_code)] // rendered through `{% include %}`
 #[derive(Template)]
 #[template(ext = "html", path = "script.html")]
 pub struct ScriptTemplate {}
 
+#[allow(dead_code)] // rendered through `{% include %}`
 #[derive(Template)]
 #[template(ext = "html", path = "style.html")]
 pub struct StyleTemplate {}
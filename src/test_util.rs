@@ -0,0 +1,59 @@
+//! Helpers shared by the unit tests: local HTTP stand-ins and site fixtures
+
+use crate::{CheckResult, SiteData, Verdict};
+use poem::http::StatusCode;
+use poem::listener::TcpAcceptor;
+use poem::web::Path;
+use poem::{IntoResponse, Route, Server, handler};
+
+/// Serves `app` on a random local port and returns its base URL
+pub async fn serve(app: Route) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = TcpAcceptor::from_tokio(listener).unwrap();
+    tokio::spawn(Server::new_with_acceptor(acceptor).run(app));
+    format!("http://{}", addr)
+}
+
+/// Profile of the `test_site` data: `johndoe` exists, nobody else does
+#[handler]
+pub fn profile(Path(name): Path<String>) -> poem::Response {
+    if name == "johndoe" {
+        r#"{"id": 1}"#.into_response()
+    } else {
+        "Not Found"
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response()
+    }
+}
+
+/// A site that claims every username exists
+#[handler]
+pub fn always_found() -> &'static str {
+    r#"{"id": 1}"#
+}
+
+/// A successful check of `site`; tests override the fields they care about
+pub fn result(site: &str, verdict: Verdict) -> CheckResult {
+    CheckResult {
+        site: site.to_string(),
+        category: "coding".to_string(),
+        verdict,
+        check_url: format!("https://example.com/{}", site),
+        profile_url: format!("https://example.com/{}", site),
+        logo_url: String::new(),
+        error: None,
+        error_kind: None,
+        attempts: 1,
+        control_verdict: None,
+        http_status: Some(200),
+        latency_ms: Some(42),
+    }
+}
+
+/// A site whose profiles answer `{"id": ...}` and missing users `404 Not Found`
+pub fn test_site(uri_check: String) -> SiteData {
+    SiteData::new("Test", uri_check, 200, "\"id\":", 404, "Not Found")
+        .with_category("misc")
+        .with_known(vec!["johndoe".to_string()])
+}
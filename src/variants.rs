@@ -0,0 +1,229 @@
+use chrono::Datelike;
+use std::collections::HashSet;
+
+/// Characters placed between the words of a name and before suffixes
+pub const SEPARATORS: [&str; 3] = ["_", ".", "-"];
+
+/// Fixed suffixes; the current year is added to them when generating
+pub const SUFFIXES: [&str; 7] = ["hq", "app", "official", "1", "2", "01", "123"];
+
+pub const PREFIXES: [&str; 3] = ["get", "try", "the"];
+
+const LEET: [(char, char); 6] = [
+    ('a', '4'),
+    ('e', '3'),
+    ('i', '1'),
+    ('o', '0'),
+    ('s', '5'),
+    ('t', '7'),
+];
+
+/// Names shorter than this are not produced by truncation
+const MIN_TRUNCATED_LEN: usize = 3;
+
+/// Which kinds of variants to generate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantOptions {
+    pub separators: bool,
+    pub suffixes: bool,
+    pub prefixes: bool,
+    pub leet: bool,
+    pub truncation: bool,
+    /// Maximum number of candidates returned, the base name included
+    pub limit: usize,
+}
+
+impl Default for VariantOptions {
+    fn default() -> Self {
+        VariantOptions {
+            separators: true,
+            suffixes: true,
+            prefixes: true,
+            leet: true,
+            truncation: true,
+            limit: 50,
+        }
+    }
+}
+
+/// Splits a name into lowercase words on spaces, separators and camelCase humps
+fn words(base: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in base.chars() {
+        if c.is_whitespace() || c == '_' || c == '.' || c == '-' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// `SUFFIXES` followed by the current year
+fn suffixes() -> Vec<String> {
+    let year = chrono::Utc::now().year().to_string();
+    SUFFIXES
+        .iter()
+        .map(|s| s.to_string())
+        .chain([year])
+        .collect()
+}
+
+fn leet_variants(name: &str) -> Vec<String> {
+    let mut variants: Vec<String> = LEET
+        .iter()
+        .filter(|(from, _)| name.contains(*from))
+        .map(|&(from, to)| name.replace(from, &to.to_string()))
+        .collect();
+    let all: String = name
+        .chars()
+        .map(|c| {
+            LEET.iter()
+                .find(|(from, _)| *from == c)
+                .map_or(c, |&(_, to)| to)
+        })
+        .collect();
+    variants.push(all);
+    variants
+}
+
+fn truncated_variants(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut variants = Vec::new();
+
+    // Drop the vowels after the first letter, like "flickr" or "tumblr"
+    let consonants: String = chars
+        .iter()
+        .enumerate()
+        .filter(|&(i, c)| i == 0 || !"aeiou".contains(*c))
+        .map(|(_, c)| c)
+        .collect();
+    if consonants.chars().count() >= MIN_TRUNCATED_LEN {
+        variants.push(consonants);
+    }
+
+    let shortest = MIN_TRUNCATED_LEN.max(chars.len().div_ceil(2));
+    for len in (shortest..chars.len()).rev() {
+        variants.push(chars[..len].iter().collect());
+    }
+    variants
+}
+
+/// Produces candidate handles derived from `base`, starting with the base
+/// name itself and without duplicates
+pub fn generate_variants(base: &str, options: &VariantOptions) -> Vec<String> {
+    let words = words(base);
+    if words.is_empty() {
+        return Vec::new();
+    }
+    let name = words.concat();
+    let suffixes = suffixes();
+    let mut candidates = vec![name.clone()];
+
+    if options.separators && words.len() > 1 {
+        for separator in SEPARATORS {
+            candidates.push(words.join(separator));
+        }
+    }
+    if options.suffixes {
+        for suffix in &suffixes {
+            candidates.push(format!("{}{}", name, suffix));
+        }
+    }
+    if options.prefixes {
+        for prefix in PREFIXES {
+            candidates.push(format!("{}{}", prefix, name));
+        }
+    }
+    if options.leet {
+        candidates.extend(leet_variants(&name));
+    }
+    if options.truncation {
+        candidates.extend(truncated_variants(&name));
+    }
+    // Separated affixes are the most numerous, so they come last and are the
+    // first to go when the limit is reached
+    if options.separators {
+        for separator in SEPARATORS {
+            if options.suffixes {
+                for suffix in &suffixes {
+                    candidates.push(format!("{}{}{}", name, separator, suffix));
+                }
+            }
+            if options.prefixes {
+                for prefix in PREFIXES {
+                    candidates.push(format!("{}{}{}", prefix, separator, name));
+                }
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    candidates.retain(|candidate| seen.insert(candidate.clone()));
+    candidates.truncate(options.limit);
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words() {
+        assert_eq!(words("First Step"), ["first", "step"]);
+        assert_eq!(words("firstStep_name"), ["first", "step", "name"]);
+        assert_eq!(words("neosb"), ["neosb"]);
+    }
+
+    #[test]
+    fn test_generate_variants() {
+        let variants = generate_variants("Nutek Security", &VariantOptions::default());
+        assert_eq!(variants[0], "nuteksecurity");
+        for expected in [
+            "nutek_security",
+            "nutek.security",
+            "nuteksecurityhq",
+            &format!("nuteksecurity{}", chrono::Utc::now().year()),
+            "nuteksecurity_official",
+            "getnuteksecurity",
+            "try.nuteksecurity",
+            "nut3ks3curity",
+            "ntkscrty",
+            "nuteksecurit",
+        ] {
+            assert!(
+                variants.iter().any(|v| v == expected),
+                "missing {}",
+                expected
+            );
+        }
+        let unique: HashSet<&String> = variants.iter().collect();
+        assert_eq!(unique.len(), variants.len());
+        assert!(variants.len() <= 50);
+
+        let options = VariantOptions {
+            separators: false,
+            suffixes: false,
+            prefixes: false,
+            leet: true,
+            truncation: false,
+            limit: 10,
+        };
+        assert_eq!(
+            generate_variants("toast", &options),
+            ["toast", "to4st", "t0ast", "toa5t", "7oas7", "70457"]
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use crate::diff::diff_reports;
+use crate::webhook::direct_client;
+use crate::{CheckOptions, Error, NullSink, Report, SiteData, Verdict, check_username_with_sink};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// A site on which a watched username went from Taken to Available
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub username: String,
+    pub site: String,
+    pub profile_url: String,
+    pub detected_at: String,
+}
+
+/// Where releases are announced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notifier {
+    /// POSTs the release as JSON
+    Webhook(String),
+    /// Runs a shell command with the release as JSON on stdin and in
+    /// `FIRSTSTEP_*` environment variables
+    Command(String),
+    /// Prints one line per release, for piping into `notify-send` and friends
+    Stdout,
+}
+
+impl Notifier {
+    /// Announces `release`; webhooks are posted with `client`
+    pub async fn notify(&self, client: &Client, release: &Release) -> Result<(), Error> {
+        match self {
+            Notifier::Webhook(url) => {
+                client
+                    .post(url)
+                    .json(release)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            Notifier::Command(command) => {
+                let mut child = shell(command)
+                    .env("FIRSTSTEP_USERNAME", &release.username)
+                    .env("FIRSTSTEP_SITE", &release.site)
+                    .env("FIRSTSTEP_PROFILE_URL", &release.profile_url)
+                    .stdin(Stdio::piped())
+                    .spawn()?;
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(&serde_json::to_vec(release)?).await?;
+                }
+                let status = child.wait().await?;
+                if !status.success() {
+                    return Err(Error::Command(status));
+                }
+            }
+            Notifier::Stdout => println!(
+                "{} is available on {}: {}",
+                release.username, release.site, release.profile_url
+            ),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> tokio::process::Command {
+    let mut shell = tokio::process::Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> tokio::process::Command {
+    let mut shell = tokio::process::Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// Re-checks a set of usernames and announces sites where they were released
+pub struct Watcher {
+    usernames: Vec<String>,
+    notifiers: Vec<Notifier>,
+    /// Direct client for webhook notifiers, never the proxied scan client
+    notify_client: Client,
+    /// Last Taken or Available result per site of every username
+    baseline: HashMap<String, Report>,
+}
+
+impl Watcher {
+    pub fn new(usernames: Vec<String>, notifiers: Vec<Notifier>) -> Self {
+        Watcher {
+            usernames,
+            notifiers,
+            notify_client: direct_client(),
+            baseline: HashMap::new(),
+        }
+    }
+
+    /// Checks every username once and notifies about Taken to Available
+    /// transitions since the last definitive verdict, so that a round where
+    /// a site failed does not hide a release. The first run only records a
+    /// baseline.
+    pub async fn run_once(
+        &mut self,
+        client: &Client,
+        sites_data: &[SiteData],
+        options: &CheckOptions,
+    ) -> Vec<Report> {
+        let mut reports = Vec::with_capacity(self.usernames.len());
+        for username in &self.usernames {
+            let results =
+                check_username_with_sink(client, username, sites_data, options, &NullSink).await;
+            let report = Report::new(username, results);
+
+            match self.baseline.get_mut(username) {
+                Some(baseline) => {
+                    for release in releases(baseline, &report) {
+                        for notifier in &self.notifiers {
+                            if let Err(e) = notifier.notify(&self.notify_client, &release).await {
+                                eprintln!("Failed to notify about {}: {}", release.site, e);
+                            }
+                        }
+                    }
+                    update_baseline(baseline, &report);
+                }
+                None => {
+                    self.baseline.insert(username.clone(), report.clone());
+                }
+            }
+            reports.push(report);
+        }
+        reports
+    }
+
+    /// Runs forever, starting a new round `every` after the previous one started
+    pub async fn run(
+        &mut self,
+        client: &Client,
+        sites_data: &[SiteData],
+        options: &CheckOptions,
+        every: Duration,
+        mut on_round: impl FnMut(&[Report]),
+    ) {
+        let mut ticker = tokio::time::interval(every);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let reports = self.run_once(client, sites_data, options).await;
+            on_round(&reports);
+        }
+    }
+}
+
+/// Replaces the baseline result of every site that got a Taken or Available
+/// verdict in `current`, keeping older ones where the check failed
+fn update_baseline(baseline: &mut Report, current: &Report) {
+    for result in &current.results {
+        let definitive = matches!(result.verdict, Verdict::Taken | Verdict::Available);
+        match baseline.results.iter_mut().find(|r| r.site == result.site) {
+            Some(known) if definitive => *known = result.clone(),
+            Some(_) => {}
+            None => baseline.results.push(result.clone()),
+        }
+    }
+    baseline.generated_at = current.generated_at.clone();
+}
+
+/// Sites that went from Taken in `previous` to Available in `current`
+pub fn releases(previous: &Report, current: &Report) -> Vec<Release> {
+    diff_reports(previous, current)
+        .flipped
+        .into_iter()
+        .filter(|change| change.before == Verdict::Taken && change.after == Verdict::Available)
+        .map(|change| Release {
+            username: current.username.clone(),
+            site: change.site,
+            profile_url: change.profile_url,
+            detected_at: current.generated_at.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{serve, test_site};
+    use poem::http::StatusCode;
+    use poem::web::Json;
+    use poem::{EndpointExt, IntoResponse, Route, get, handler, post};
+    use std::sync::atomic::{AtomicU8, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    const TAKEN: u8 = 0;
+    const BROKEN: u8 = 1;
+    const FREE: u8 = 2;
+
+    #[handler]
+    fn profile(state: poem::web::Data<&Arc<AtomicU8>>) -> poem::Response {
+        match state.load(Ordering::SeqCst) {
+            TAKEN => r#"{"id": 1}"#.into_response(),
+            BROKEN => "Oops"
+                .with_status(StatusCode::INTERNAL_SERVER_ERROR)
+                .into_response(),
+            _ => "Not Found"
+                .with_status(StatusCode::NOT_FOUND)
+                .into_response(),
+        }
+    }
+
+    #[handler]
+    fn webhook(
+        Json(release): Json<Release>,
+        received: poem::web::Data<&Arc<Mutex<Vec<Release>>>>,
+    ) -> StatusCode {
+        received.lock().unwrap().push(release);
+        StatusCode::NO_CONTENT
+    }
+
+    #[tokio::test]
+    async fn test_watcher_notifies_on_release() {
+        let state = Arc::new(AtomicU8::new(TAKEN));
+        let received: Arc<Mutex<Vec<Release>>> = Arc::default();
+        let base = serve(
+            Route::new()
+                .at("/users/:name", get(profile).data(state.clone()))
+                .at("/hook", post(webhook).data(received.clone())),
+        )
+        .await;
+        let sites = [test_site(format!("{}/users/{{account}}", base))];
+
+        let client = Client::new();
+        let options = CheckOptions::default();
+        let mut watcher = Watcher::new(
+            vec!["johndoe".to_string()],
+            vec![Notifier::Webhook(format!("{}/hook", base))],
+        );
+
+        watcher.run_once(&client, &sites, &options).await;
+        watcher.run_once(&client, &sites, &options).await;
+        assert!(received.lock().unwrap().is_empty(), "still taken");
+
+        state.store(BROKEN, Ordering::SeqCst);
+        let reports = watcher.run_once(&client, &sites, &options).await;
+        assert_eq!(reports[0].results[0].verdict, Verdict::Inconclusive);
+        assert!(
+            received.lock().unwrap().is_empty(),
+            "a failed round is no release"
+        );
+
+        state.store(FREE, Ordering::SeqCst);
+        let reports = watcher.run_once(&client, &sites, &options).await;
+        assert_eq!(reports[0].results[0].verdict, Verdict::Available);
+        {
+            let received = received.lock().unwrap();
+            assert_eq!(received.len(), 1);
+            assert_eq!(received[0].username, "johndoe");
+            assert_eq!(received[0].site, "Test");
+        }
+
+        watcher.run_once(&client, &sites, &options).await;
+        assert_eq!(
+            received.lock().unwrap().len(),
+            1,
+            "staying available is not a new release"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_notifier() {
+        let path = std::env::temp_dir().join("firststep_name_watch_hook.txt");
+        let _ = std::fs::remove_file(&path);
+        let notifier = Notifier::Command(format!(
+            "echo \"$FIRSTSTEP_USERNAME@$FIRSTSTEP_SITE\" > {}; cat >> {}",
+            path.display(),
+            path.display()
+        ));
+        let release = Release {
+            username: "johndoe".to_string(),
+            site: "GitHub".to_string(),
+            profile_url: "https://github.com/johndoe".to_string(),
+            detected_at: "2025-03-01".to_string(),
+        };
+
+        notifier.notify(&Client::new(), &release).await.unwrap();
+        let output = std::fs::read_to_string(&path).unwrap();
+        assert!(output.starts_with("johndoe@GitHub\n{"));
+        assert!(output.contains(r#""profile_url":"https://github.com/johndoe""#));
+        std::fs::remove_file(path).unwrap();
+
+        let failing = Notifier::Command("exit 3".to_string());
+        assert!(failing.notify(&Client::new(), &release).await.is_err());
+    }
+}
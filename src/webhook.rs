@@ -0,0 +1,234 @@
+use crate::report::count_verdicts;
+use crate::retry::is_transient_status;
+use crate::{CheckResult, Error, Report, RetryPolicy, Verdict};
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Header carrying `sha256=<hex HMAC of the body>` when a secret is configured
+pub const SIGNATURE_HEADER: &str = "X-Firststep-Signature";
+/// Header naming the event, always `scan.completed` for now
+pub const EVENT_HEADER: &str = "X-Firststep-Event";
+
+const COMPLETED_EVENT: &str = "scan.completed";
+
+/// Body POSTed to every completion webhook
+#[derive(Serialize, Debug)]
+struct CompletionPayload<'a> {
+    event: &'static str,
+    username: &'a str,
+    generated_at: &'a str,
+    /// Number of results per verdict key, zero counts included
+    summary: BTreeMap<&'static str, usize>,
+    results: &'a [CheckResult],
+}
+
+impl<'a> CompletionPayload<'a> {
+    fn new(report: &'a Report) -> Self {
+        let summary = Verdict::ALL
+            .iter()
+            .map(|&verdict| (verdict.key(), count_verdicts(&report.results, verdict)))
+            .collect();
+        CompletionPayload {
+            event: COMPLETED_EVENT,
+            username: &report.username,
+            generated_at: &report.generated_at,
+            summary,
+            results: &report.results,
+        }
+    }
+}
+
+/// Hex encoded HMAC-SHA256 of `body` keyed with `secret`
+pub fn sign(secret: &str, body: &[u8]) -> Result<String, openssl::error::ErrorStack> {
+    let key = PKey::hmac(secret.as_bytes())?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    signer.update(body)?;
+    let mac = signer.sign_to_vec()?;
+    Ok(mac.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Time allowed for a single delivery attempt
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Endpoints told about every finished scan
+#[derive(Debug, Clone)]
+pub struct CompletionWebhooks {
+    pub urls: Vec<String>,
+    /// Key for the signature header, unsigned deliveries when `None`
+    pub secret: Option<String>,
+    /// Applied to connection failures and 5xx or 429 answers
+    pub retry: RetryPolicy,
+    /// Direct client, so that signed payloads never go through scan proxies
+    client: Client,
+}
+
+/// Client for outgoing notifications: direct, so that hooks on localhost or
+/// an internal network are never reached through the scan proxies
+pub(crate) fn direct_client() -> Client {
+    Client::builder()
+        .no_proxy()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+impl Default for CompletionWebhooks {
+    fn default() -> Self {
+        CompletionWebhooks {
+            urls: Vec::new(),
+            secret: None,
+            retry: RetryPolicy::default(),
+            client: direct_client(),
+        }
+    }
+}
+
+impl CompletionWebhooks {
+    pub fn new(urls: Vec<String>, secret: Option<String>) -> Self {
+        CompletionWebhooks {
+            urls,
+            secret,
+            ..CompletionWebhooks::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+
+    /// POSTs the report to every URL; failures only warn so that one broken
+    /// endpoint does not hold up the others
+    pub async fn deliver(&self, report: &Report) {
+        if self.is_empty() {
+            return;
+        }
+        let body = match serde_json::to_vec(&CompletionPayload::new(report)) {
+            Ok(body) => body,
+            Err(e) => {
+                eprintln!("Failed to serialize webhook payload: {}", e);
+                return;
+            }
+        };
+        let signature = match self.secret.as_deref().map(|secret| sign(secret, &body)) {
+            Some(Ok(signature)) => Some(format!("sha256={}", signature)),
+            Some(Err(e)) => {
+                eprintln!("Failed to sign webhook payload: {}", e);
+                return;
+            }
+            None => None,
+        };
+        for url in &self.urls {
+            if let Err(e) = self.post(url, &body, signature.as_deref()).await {
+                eprintln!("Failed to deliver completion webhook to {}: {}", url, e);
+            }
+        }
+    }
+
+    async fn post(&self, url: &str, body: &[u8], signature: Option<&str>) -> Result<(), Error> {
+        let mut attempt = 1;
+        loop {
+            let mut request = self
+                .client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, COMPLETED_EVENT)
+                .body(body.to_vec());
+            if let Some(signature) = signature {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+
+            let error = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status().as_u16();
+                    let error = Error::Status {
+                        url: url.to_string(),
+                        status,
+                    };
+                    if !response.status().is_server_error() && !is_transient_status(status) {
+                        return Err(error);
+                    }
+                    error
+                }
+                Err(e) => e.into(),
+            };
+            match self.retry.next_delay(attempt, None) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return Err(error),
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::serve;
+    use poem::http::{HeaderMap, StatusCode};
+    use poem::web::Data;
+    use poem::{EndpointExt, Route, handler, post};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?").unwrap(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    type Deliveries = Arc<Mutex<Vec<(Option<String>, Vec<u8>)>>>;
+
+    /// Fails the first delivery with 503 and records the later ones
+    #[handler]
+    fn hook(headers: &HeaderMap, body: Vec<u8>, received: Data<&Deliveries>) -> StatusCode {
+        let mut received = received.lock().unwrap();
+        let signature = headers
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        received.push((signature, body));
+        if received.len() == 1 {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::NO_CONTENT
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deliver_signs_and_retries() {
+        let received = Deliveries::default();
+        let base = serve(Route::new().at("/hook", post(hook).data(received.clone()))).await;
+        let webhooks = CompletionWebhooks {
+            urls: vec![format!("{}/hook", base)],
+            secret: Some("s3cret".to_string()),
+            retry: RetryPolicy {
+                base_delay: Duration::from_millis(1),
+                ..RetryPolicy::default()
+            },
+            ..CompletionWebhooks::default()
+        };
+
+        let report = Report::new("johndoe", Vec::new());
+        webhooks.deliver(&report).await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2, "the 503 is retried");
+        let (signature, body) = &received[1];
+        assert_eq!(
+            signature.as_deref(),
+            Some(format!("sha256={}", sign("s3cret", body).unwrap()).as_str())
+        );
+        let payload: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(payload["event"], "scan.completed");
+        assert_eq!(payload["username"], "johndoe");
+        assert_eq!(payload["summary"]["taken"], 0);
+    }
+}